@@ -0,0 +1,627 @@
+use luau_sys::common::bytecode::{LuauBytecodeTag, LuauOpcode};
+use std::{error::Error, fmt::Display};
+
+/// Bytecode produced by [`compile`](crate::compile), parsed into its parts
+///
+/// References to strings are indices into [`Bytecode::strings`], and references to functions are indices into
+/// [`Bytecode::protos`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bytecode {
+    pub version: u8,
+    /// Version of the type information of functions, 0 for bytecode versions that have none
+    pub types_version: u8,
+    /// Strings used by the constants and the debug information
+    pub strings: Vec<Vec<u8>>,
+    /// Userdata types declared with [`set_userdata_types`](crate::CompilerOptions::set_userdata_types), as
+    /// the index used in the type information and the name
+    pub userdata_types: Vec<(u8, usize)>,
+    /// All functions in the chunk, children always come before their parents
+    pub protos: Vec<Proto>,
+    /// The function that runs the chunk
+    pub main: usize,
+}
+
+/// A function in [`Bytecode`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proto {
+    pub max_stack_size: u8,
+    pub num_params: u8,
+    pub num_upvalues: u8,
+    pub is_vararg: bool,
+    /// `LuauProtoFlag` bits
+    pub flags: u8,
+    /// Encoded types of the arguments, upvalues and locals, used for native code generation
+    pub type_info: Vec<u8>,
+    /// Instructions and their auxiliary words, see [`Proto::instructions`]
+    pub code: Vec<u32>,
+    pub constants: Vec<Constant>,
+    /// Functions defined in this function
+    pub children: Vec<usize>,
+    pub line_defined: u32,
+    pub debug_name: Option<usize>,
+    pub line_info: Option<LineInfo>,
+    pub debug_info: Option<DebugInfo>,
+}
+
+/// A constant of a [`Proto`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    /// Always has 4 components in bytecode, whatever the vector size of the VM
+    Vector([f32; 4]),
+    String(usize),
+    /// A global or a path of up to 3 names (`math.max`), see [`Import`]
+    Import(Import),
+    /// Template of a table created by `DUPTABLE`, with the constant indices of its string keys
+    Table(Vec<u32>),
+    /// A function that doesn't capture anything, so it can be shared by `DUPCLOSURE`
+    Closure(usize),
+}
+
+/// Packed path of an import, up to 3 indices of string constants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Import(pub u32);
+
+impl Import {
+    /// Constant indices of the names in the path
+    pub fn path(&self) -> impl Iterator<Item = u32> {
+        let id = self.0;
+
+        [20, 10, 0]
+            .into_iter()
+            .take((id >> 30) as usize)
+            .map(move |shift| (id >> shift) & 1023)
+    }
+}
+
+/// Line of each instruction of a [`Proto`]
+///
+/// Stored as absolute lines for every `2^gap_log2` instructions, plus an offset from them for each instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineInfo {
+    pub gap_log2: u8,
+    pub offsets: Vec<u8>,
+    pub absolute: Vec<i32>,
+}
+
+impl LineInfo {
+    /// Line of the instruction at `pc`
+    pub fn line(&self, pc: usize) -> Option<i32> {
+        let base = self.absolute.get(pc >> self.gap_log2)?;
+
+        Some(base + *self.offsets.get(pc)? as i32)
+    }
+}
+
+/// Names of locals and upvalues of a [`Proto`], only present with [`DebugLevel::Full`](crate::DebugLevel::Full)
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugInfo {
+    pub locals: Vec<Local>,
+    pub upvalues: Vec<Option<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Local {
+    pub name: Option<usize>,
+    /// First instruction where the local is alive
+    pub start_pc: u32,
+    /// Instruction after the last one where the local is alive
+    pub end_pc: u32,
+    pub register: u8,
+}
+
+/// Errors that can happen when parsing bytecode, see [`Bytecode::parse`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum BytecodeError {
+    /// The buffer ends in the middle of the bytecode
+    Truncated,
+    /// The bytecode was produced by an unsupported version of the compiler
+    Version { version: u8, min: u8, max: u8 },
+    /// The type information is in an unsupported format
+    TypesVersion { version: u8, min: u8, max: u8 },
+    /// The buffer contains a compile error instead of bytecode
+    Compile(String),
+    /// The bytecode is malformed, with a description of what's wrong
+    Invalid(String),
+}
+
+/// Tags of constants, `LBC_CONSTANT_*` in `Bytecode.h`
+const CONSTANT_NIL: u8 = 0;
+const CONSTANT_BOOLEAN: u8 = 1;
+const CONSTANT_NUMBER: u8 = 2;
+const CONSTANT_STRING: u8 = 3;
+const CONSTANT_IMPORT: u8 = 4;
+const CONSTANT_TABLE: u8 = 5;
+const CONSTANT_CLOSURE: u8 = 6;
+const CONSTANT_VECTOR: u8 = 7;
+
+impl Bytecode {
+    /// Parses the bytecode, following the same format as `luau_load` in the VM
+    ///
+    /// Only the structure is checked, not the instructions.
+    pub fn parse(bytecode: &[u8]) -> Result<Self, BytecodeError> {
+        let mut r = Reader {
+            data: bytecode,
+            offset: 0,
+        };
+
+        let version = r.u8()?;
+        if version == 0 {
+            // compile errors are encoded as a null byte followed by the message
+            let message = String::from_utf8_lossy(&bytecode[1..]);
+            return Err(BytecodeError::Compile(
+                message.trim_start_matches(':').to_owned(),
+            ));
+        }
+
+        let min = LuauBytecodeTag::LBC_VERSION_MIN.0 as u8;
+        let max = LuauBytecodeTag::LBC_VERSION_MAX.0 as u8;
+        if !(min..=max).contains(&version) {
+            return Err(BytecodeError::Version { version, min, max });
+        }
+
+        let mut types_version = 0;
+        if version >= 4 {
+            types_version = r.u8()?;
+
+            let min = LuauBytecodeTag::LBC_TYPE_VERSION_MIN.0 as u8;
+            let max = LuauBytecodeTag::LBC_TYPE_VERSION_MAX.0 as u8;
+            if !(min..=max).contains(&types_version) {
+                return Err(BytecodeError::TypesVersion {
+                    version: types_version,
+                    min,
+                    max,
+                });
+            }
+        }
+
+        let string_count = r.varint()?;
+        let strings = (0..string_count)
+            .map(|_| {
+                let len = r.varint()?;
+                Ok(r.bytes(len as usize)?.to_vec())
+            })
+            .collect::<Result<Vec<_>, BytecodeError>>()?;
+
+        let mut bytecode = Bytecode {
+            version,
+            types_version,
+            strings,
+            userdata_types: Vec::new(),
+            protos: Vec::new(),
+            main: 0,
+        };
+
+        if types_version == 3 {
+            loop {
+                let index = r.u8()?;
+                if index == 0 {
+                    break;
+                }
+
+                let name = bytecode
+                    .string_ref(&mut r)?
+                    .ok_or_else(|| invalid("userdata type without a name"))?;
+                bytecode.userdata_types.push((index - 1, name));
+            }
+        }
+
+        let proto_count = r.varint()?;
+        for i in 0..proto_count as usize {
+            let proto = bytecode.parse_proto(&mut r, i)?;
+            bytecode.protos.push(proto);
+        }
+
+        bytecode.main = bytecode.proto_ref(&mut r, bytecode.protos.len())?;
+
+        Ok(bytecode)
+    }
+    fn parse_proto(&self, r: &mut Reader, index: usize) -> Result<Proto, BytecodeError> {
+        let max_stack_size = r.u8()?;
+        let num_params = r.u8()?;
+        let num_upvalues = r.u8()?;
+        let is_vararg = r.u8()? != 0;
+
+        let mut flags = 0;
+        let mut type_info = Vec::new();
+        if self.version >= 4 {
+            flags = r.u8()?;
+
+            let len = r.varint()?;
+            type_info = r.bytes(len as usize)?.to_vec();
+        }
+
+        let code_len = r.varint()?;
+        let code = (0..code_len)
+            .map(|_| r.u32())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let constant_count = r.varint()?;
+        let mut constants = Vec::new();
+        for _ in 0..constant_count {
+            constants.push(match r.u8()? {
+                CONSTANT_NIL => Constant::Nil,
+                CONSTANT_BOOLEAN => Constant::Boolean(r.u8()? != 0),
+                CONSTANT_NUMBER => Constant::Number(f64::from_le_bytes(r.array()?)),
+                CONSTANT_VECTOR => Constant::Vector([
+                    f32::from_le_bytes(r.array()?),
+                    f32::from_le_bytes(r.array()?),
+                    f32::from_le_bytes(r.array()?),
+                    f32::from_le_bytes(r.array()?),
+                ]),
+                CONSTANT_STRING => Constant::String(
+                    self.string_ref(r)?
+                        .ok_or_else(|| invalid("string constant without a string"))?,
+                ),
+                CONSTANT_IMPORT => Constant::Import(Import(r.u32()?)),
+                CONSTANT_TABLE => {
+                    let len = r.varint()?;
+                    Constant::Table((0..len).map(|_| r.varint()).collect::<Result<_, _>>()?)
+                }
+                // functions can only refer to the ones before them
+                CONSTANT_CLOSURE => Constant::Closure(self.proto_ref(r, index)?),
+                tag => return Err(invalid(format!("unknown constant type {tag}"))),
+            });
+        }
+
+        let child_count = r.varint()?;
+        let children = (0..child_count)
+            .map(|_| self.proto_ref(r, index))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let line_defined = r.varint()?;
+        let debug_name = self.string_ref(r)?;
+
+        let line_info = match r.u8()? {
+            0 => None,
+            _ => {
+                let gap_log2 = r.u8()?;
+                if gap_log2 >= 32 {
+                    return Err(invalid("line gap is too large"));
+                }
+                // luau writes no absolute lines for functions without code
+                let intervals = match code.len() {
+                    0 => 0,
+                    len => ((len - 1) >> gap_log2) + 1,
+                };
+
+                let mut last = 0u8;
+                let offsets = (0..code.len())
+                    .map(|_| {
+                        last = last.wrapping_add(r.u8()?);
+                        Ok(last)
+                    })
+                    .collect::<Result<Vec<_>, BytecodeError>>()?;
+
+                let mut last = 0i32;
+                let absolute = (0..intervals)
+                    .map(|_| {
+                        last = last.wrapping_add(i32::from_le_bytes(r.array()?));
+                        Ok(last)
+                    })
+                    .collect::<Result<Vec<_>, BytecodeError>>()?;
+
+                Some(LineInfo {
+                    gap_log2,
+                    offsets,
+                    absolute,
+                })
+            }
+        };
+
+        let debug_info = match r.u8()? {
+            0 => None,
+            _ => {
+                let local_count = r.varint()?;
+                let locals = (0..local_count)
+                    .map(|_| {
+                        Ok(Local {
+                            name: self.string_ref(r)?,
+                            start_pc: r.varint()?,
+                            end_pc: r.varint()?,
+                            register: r.u8()?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, BytecodeError>>()?;
+
+                let upvalue_count = r.varint()?;
+                let upvalues = (0..upvalue_count)
+                    .map(|_| self.string_ref(r))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Some(DebugInfo { locals, upvalues })
+            }
+        };
+
+        Ok(Proto {
+            max_stack_size,
+            num_params,
+            num_upvalues,
+            is_vararg,
+            flags,
+            type_info,
+            code,
+            constants,
+            children,
+            line_defined,
+            debug_name,
+            line_info,
+            debug_info,
+        })
+    }
+    /// Reads a reference to a string, 0 meaning no string
+    fn string_ref(&self, r: &mut Reader) -> Result<Option<usize>, BytecodeError> {
+        match r.varint()? as usize {
+            0 => Ok(None),
+            i if i <= self.strings.len() => Ok(Some(i - 1)),
+            i => Err(invalid(format!("string {i} out of range"))),
+        }
+    }
+    /// Reads a reference to one of the first `count` functions
+    fn proto_ref(&self, r: &mut Reader, count: usize) -> Result<usize, BytecodeError> {
+        let i = r.varint()? as usize;
+        if i >= count {
+            return Err(invalid(format!("function {i} out of range")));
+        }
+
+        Ok(i)
+    }
+    /// Bytes of the string at `index`
+    pub fn string(&self, index: usize) -> Option<&[u8]> {
+        self.strings.get(index).map(Vec::as_slice)
+    }
+}
+
+pub(crate) fn invalid(message: impl Into<String>) -> BytecodeError {
+    BytecodeError::Invalid(message.into())
+}
+
+pub(crate) struct Reader<'d> {
+    pub(crate) data: &'d [u8],
+    pub(crate) offset: usize,
+}
+
+impl<'d> Reader<'d> {
+    pub(crate) fn bytes(&mut self, len: usize) -> Result<&'d [u8], BytecodeError> {
+        let bytes = self
+            .data
+            .get(self.offset..)
+            .and_then(|rest| rest.get(..len))
+            .ok_or(BytecodeError::Truncated)?;
+        self.offset += len;
+
+        Ok(bytes)
+    }
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BytecodeError> {
+        // the length is right
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+    pub(crate) fn u8(&mut self) -> Result<u8, BytecodeError> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn u32(&mut self) -> Result<u32, BytecodeError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+    /// LEB128, at most 5 bytes
+    pub(crate) fn varint(&mut self) -> Result<u32, BytecodeError> {
+        let mut result = 0;
+
+        for shift in (0..35).step_by(7) {
+            let byte = self.u8()?;
+            result |= ((byte & 127) as u32) << shift;
+
+            if byte & 128 == 0 {
+                return Ok(result);
+            }
+        }
+
+        Err(invalid("variable length integer is too long"))
+    }
+}
+
+/// A decoded instruction of a [`Proto`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub word: u32,
+    /// The word after the instruction, for opcodes that have one
+    pub aux: Option<u32>,
+}
+
+impl Instruction {
+    pub fn op(&self) -> LuauOpcode {
+        LuauOpcode((self.word & 0xff) as _)
+    }
+    pub fn a(&self) -> u8 {
+        (self.word >> 8) as u8
+    }
+    pub fn b(&self) -> u8 {
+        (self.word >> 16) as u8
+    }
+    pub fn c(&self) -> u8 {
+        (self.word >> 24) as u8
+    }
+    /// Signed 16 bit operand in place of `B` and `C`
+    pub fn d(&self) -> i32 {
+        self.word as i32 >> 16
+    }
+    /// Signed 24 bit operand in place of `A`, `B` and `C`
+    pub fn e(&self) -> i32 {
+        self.word as i32 >> 8
+    }
+}
+
+impl Proto {
+    /// The instructions with their positions in [`Proto::code`]
+    ///
+    /// Unknown opcodes are returned without an auxiliary word, and an auxiliary word missing at the end is
+    /// returned as `None`.
+    pub fn instructions(&self) -> impl Iterator<Item = (usize, Instruction)> + '_ {
+        let mut pc = 0;
+
+        std::iter::from_fn(move || {
+            let start = pc;
+            let word = *self.code.get(pc)?;
+
+            let has_aux = op_info(LuauOpcode((word & 0xff) as _)).is_some_and(|info| info.aux);
+            let aux = has_aux.then(|| self.code.get(pc + 1).copied()).flatten();
+            pc += 1 + has_aux as usize;
+
+            Some((start, Instruction { word, aux }))
+        })
+    }
+    /// Line of the instruction at `pc`, if the line information was kept
+    pub fn line(&self, pc: usize) -> Option<i32> {
+        self.line_info.as_ref()?.line(pc)
+    }
+}
+
+/// Which operands an opcode uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operands {
+    None,
+    A,
+    AB,
+    ABC,
+    AD,
+    E,
+}
+
+/// Description of an opcode, see [`op_info`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpInfo {
+    /// Name without the `LOP_` prefix
+    pub name: &'static str,
+    pub operands: Operands,
+    /// Whether the instruction is followed by an auxiliary word
+    pub aux: bool,
+    /// Whether `D` (or `E` for `JUMPX`) is a jump offset, relative to the next instruction
+    pub jump: bool,
+}
+
+macro_rules! opcodes {
+    ($($op:ident: $operands:ident $(+ $extra:ident)*),* $(,)?) => {
+        /// Describes the opcode, `None` if it's not known
+        pub fn op_info(op: LuauOpcode) -> Option<OpInfo> {
+            Some(match op {
+                $(LuauOpcode::$op => OpInfo {
+                    name: &stringify!($op)[4..],
+                    operands: Operands::$operands,
+                    aux: false $(|| opcodes!(@is aux $extra))*,
+                    jump: false $(|| opcodes!(@is jump $extra))*,
+                },)*
+                _ => return None,
+            })
+        }
+    };
+    (@is aux aux) => { true };
+    (@is jump jump) => { true };
+    (@is $a:ident $b:ident) => { false };
+}
+
+opcodes! {
+    LOP_NOP: None,
+    LOP_BREAK: None,
+    LOP_LOADNIL: A,
+    LOP_LOADB: ABC,
+    LOP_LOADN: AD,
+    LOP_LOADK: AD,
+    LOP_MOVE: AB,
+    LOP_GETGLOBAL: ABC + aux,
+    LOP_SETGLOBAL: ABC + aux,
+    LOP_GETUPVAL: AB,
+    LOP_SETUPVAL: AB,
+    LOP_CLOSEUPVALS: A,
+    LOP_GETIMPORT: AD + aux,
+    LOP_GETTABLE: ABC,
+    LOP_SETTABLE: ABC,
+    LOP_GETTABLEKS: ABC + aux,
+    LOP_SETTABLEKS: ABC + aux,
+    LOP_GETTABLEN: ABC,
+    LOP_SETTABLEN: ABC,
+    LOP_NEWCLOSURE: AD,
+    LOP_NAMECALL: ABC + aux,
+    LOP_CALL: ABC,
+    LOP_RETURN: AB,
+    LOP_JUMP: AD + jump,
+    LOP_JUMPBACK: AD + jump,
+    LOP_JUMPIF: AD + jump,
+    LOP_JUMPIFNOT: AD + jump,
+    LOP_JUMPIFEQ: AD + aux + jump,
+    LOP_JUMPIFLE: AD + aux + jump,
+    LOP_JUMPIFLT: AD + aux + jump,
+    LOP_JUMPIFNOTEQ: AD + aux + jump,
+    LOP_JUMPIFNOTLE: AD + aux + jump,
+    LOP_JUMPIFNOTLT: AD + aux + jump,
+    LOP_ADD: ABC,
+    LOP_SUB: ABC,
+    LOP_MUL: ABC,
+    LOP_DIV: ABC,
+    LOP_MOD: ABC,
+    LOP_POW: ABC,
+    LOP_ADDK: ABC,
+    LOP_SUBK: ABC,
+    LOP_MULK: ABC,
+    LOP_DIVK: ABC,
+    LOP_MODK: ABC,
+    LOP_POWK: ABC,
+    LOP_AND: ABC,
+    LOP_OR: ABC,
+    LOP_ANDK: ABC,
+    LOP_ORK: ABC,
+    LOP_CONCAT: ABC,
+    LOP_NOT: AB,
+    LOP_MINUS: AB,
+    LOP_LENGTH: AB,
+    LOP_NEWTABLE: AB + aux,
+    LOP_DUPTABLE: AD,
+    LOP_SETLIST: ABC + aux,
+    LOP_FORNPREP: AD + jump,
+    LOP_FORNLOOP: AD + jump,
+    LOP_FORGLOOP: AD + aux + jump,
+    LOP_FORGPREP_INEXT: AD + jump,
+    LOP_FASTCALL3: ABC + aux,
+    LOP_FORGPREP_NEXT: AD + jump,
+    LOP_NATIVECALL: None,
+    LOP_GETVARARGS: AB,
+    LOP_DUPCLOSURE: AD,
+    LOP_PREPVARARGS: A,
+    LOP_LOADKX: A + aux,
+    LOP_JUMPX: E + jump,
+    LOP_FASTCALL: ABC,
+    LOP_COVERAGE: E,
+    LOP_CAPTURE: AB,
+    LOP_SUBRK: ABC,
+    LOP_DIVRK: ABC,
+    LOP_FASTCALL1: ABC,
+    LOP_FASTCALL2: ABC + aux,
+    LOP_FASTCALL2K: ABC + aux,
+    LOP_FORGPREP: AD + jump,
+    LOP_JUMPXEQKNIL: AD + aux + jump,
+    LOP_JUMPXEQKB: AD + aux + jump,
+    LOP_JUMPXEQKN: AD + aux + jump,
+    LOP_JUMPXEQKS: AD + aux + jump,
+    LOP_IDIV: ABC,
+    LOP_IDIVK: ABC,
+}
+
+impl Display for BytecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BytecodeError::Truncated => write!(f, "bytecode is truncated"),
+            BytecodeError::Version { version, min, max } => write!(
+                f,
+                "bytecode version mismatch (expected [{min}..{max}], got {version})"
+            ),
+            BytecodeError::TypesVersion { version, min, max } => write!(
+                f,
+                "bytecode type information version mismatch (expected [{min}..{max}], got {version})"
+            ),
+            BytecodeError::Compile(msg) => write!(f, "bytecode contains a compile error: {msg}"),
+            BytecodeError::Invalid(msg) => write!(f, "invalid bytecode: {msg}"),
+        }
+    }
+}
+
+impl Error for BytecodeError {}
@@ -0,0 +1,369 @@
+//! Caching compiled bytecode across compilations and process runs
+//!
+//! [`CompileCache`] looks up bytecode by a [`CacheKey`] made of hashes of the source code, the
+//! [`CompilerOptions`] and the version of the vendored Luau, so entries written by a different version of
+//! Luau are never used. Where the bytecode is kept is up to the [`CacheBackend`], [`MemoryCache`] keeps it in
+//! memory and [`DiskCache`] in a directory.
+
+use crate::{compile, CompileError, CompilerOptions};
+use luau_sys::{common::bytecode::LuauBytecodeTag, LUAU_COMPILER_HASH};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs,
+    hash::Hasher,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Identifies the bytecode of some source code compiled with some options
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Hash of the source code
+    pub source: u64,
+    /// Hash of the compiler options
+    pub options: u64,
+    /// Hash of the sources of the vendored Luau compiler and the bytecode versions it supports
+    pub version: u64,
+    /// SHA-256 of the source code and the compiler options together, the short hashes above can collide
+    pub digest: [u8; 32],
+}
+
+impl CacheKey {
+    pub fn new(source: &str, options: &CompilerOptions) -> Self {
+        let mut digest = DigestHasher(Sha256::new());
+        digest.write_usize(source.len());
+        digest.write(source.as_bytes());
+        options.stable_hash(&mut digest);
+
+        let mut hasher = StableHasher::new();
+        hasher.write(source.as_bytes());
+        let source = hasher.finish();
+
+        let mut hasher = StableHasher::new();
+        options.stable_hash(&mut hasher);
+        let options = hasher.finish();
+
+        Self {
+            source,
+            options,
+            version: bytecode_version(),
+            digest: digest.0.finalize().into(),
+        }
+    }
+}
+
+/// Hash of the sources of the vendored Luau compiler and its bytecode versions, changes whenever they do
+///
+/// The compiler can produce different bytecode without a new bytecode version, so its sources are part of
+/// it too.
+fn bytecode_version() -> u64 {
+    let mut hasher = StableHasher::new();
+    hasher.write(LUAU_COMPILER_HASH.as_bytes());
+    for tag in [
+        LuauBytecodeTag::LBC_VERSION_MIN,
+        LuauBytecodeTag::LBC_VERSION_MAX,
+        LuauBytecodeTag::LBC_VERSION_TARGET,
+        LuauBytecodeTag::LBC_TYPE_VERSION_MIN,
+        LuauBytecodeTag::LBC_TYPE_VERSION_MAX,
+        LuauBytecodeTag::LBC_TYPE_VERSION_TARGET,
+    ] {
+        hasher.write_u32(tag.0 as u32);
+    }
+
+    hasher.finish()
+}
+
+/// 64 bit FNV-1a, which unlike the hasher of the standard library gives the same hashes on every platform
+/// and Rust version, so they can be written to disk
+pub(crate) struct StableHasher(u64);
+
+impl StableHasher {
+    pub(crate) fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+/// Feeds the bytes to SHA-256, with integers in the same byte order as [`StableHasher`]
+struct DigestHasher(Sha256);
+
+impl Hasher for DigestHasher {
+    /// Only the digest is used, this is the start of it
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().unwrap())
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+/// Where a [`CompileCache`] keeps the bytecode
+pub trait CacheBackend {
+    fn get(&mut self, key: &CacheKey) -> Option<Arc<[u8]>>;
+    fn insert(&mut self, key: CacheKey, bytecode: Arc<[u8]>);
+}
+
+/// Compiles source code, reusing the bytecode of earlier compilations of the same code with the same options
+///
+/// Compile errors are not cached.
+pub struct CompileCache<B = MemoryCache> {
+    backend: B,
+    hits: u64,
+    misses: u64,
+}
+
+impl<B: CacheBackend> CompileCache<B> {
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            hits: 0,
+            misses: 0,
+        }
+    }
+    /// Like [`compile`], but returns the cached bytecode if there is any
+    pub fn compile(
+        &mut self,
+        source: &str,
+        options: &CompilerOptions,
+    ) -> Result<Arc<[u8]>, CompileError> {
+        let key = CacheKey::new(source, options);
+
+        if let Some(bytecode) = self.backend.get(&key) {
+            self.hits += 1;
+            return Ok(bytecode);
+        }
+        self.misses += 1;
+
+        let bytecode: Arc<[u8]> = Arc::from(&*compile(source, options)?);
+        self.backend.insert(key, bytecode.clone());
+
+        Ok(bytecode)
+    }
+    /// How many times the bytecode was found in the cache
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+    /// How many times the source code had to be compiled
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+    pub fn into_backend(self) -> B {
+        self.backend
+    }
+}
+
+impl Default for CompileCache<MemoryCache> {
+    fn default() -> Self {
+        Self::new(MemoryCache::default())
+    }
+}
+
+/// Keeps up to `capacity` entries in memory, dropping the least recently used one when full
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    entries: HashMap<CacheKey, (Arc<[u8]>, u64)>,
+    /// incremented on every access, the entry with the lowest stamp is the least recently used
+    clock: u64,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self::new(128)
+    }
+}
+
+impl CacheBackend for MemoryCache {
+    fn get(&mut self, key: &CacheKey) -> Option<Arc<[u8]>> {
+        self.clock += 1;
+
+        let (bytecode, used) = self.entries.get_mut(key)?;
+        *used = self.clock;
+
+        Some(bytecode.clone())
+    }
+    fn insert(&mut self, key: CacheKey, bytecode: Arc<[u8]>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.clock += 1;
+
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.entries.insert(key, (bytecode, self.clock));
+    }
+}
+
+/// Keeps the bytecode in files in a directory, named after the [`CacheKey`]
+///
+/// Each file starts with the [`CacheKey::digest`], so entries whose short hashes collide are not mixed up.
+/// The cache is best effort, files that can't be read or written are treated as missing.
+#[derive(Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    const EXTENSION: &str = "luauc";
+
+    /// Uses the directory for the cache, creating it if it doesn't exist
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self { dir })
+    }
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    fn path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(format!(
+            "{:016x}-{:016x}-{:016x}.{}",
+            key.source,
+            key.options,
+            key.version,
+            Self::EXTENSION
+        ))
+    }
+    /// Removes the entries written by a different version of Luau, returns how many
+    ///
+    /// They are never used, but stay on disk until removed. Temporary files left behind by interrupted writes
+    /// are removed too.
+    pub fn prune(&self) -> io::Result<usize> {
+        let current = format!("-{:016x}", bytecode_version());
+        let mut removed = 0;
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|e| e != Self::EXTENSION) {
+                continue;
+            }
+
+            let stale = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .is_none_or(|s| !s.ends_with(&current));
+            if stale {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+    /// Removes all entries
+    pub fn clear(&self) -> io::Result<()> {
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == Self::EXTENSION) {
+                fs::remove_file(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl CacheBackend for DiskCache {
+    fn get(&mut self, key: &CacheKey) -> Option<Arc<[u8]>> {
+        let mut bytecode = fs::read(self.path(key)).ok()?;
+
+        // the hashes in the file name collided with other source code or options
+        if bytecode.get(..key.digest.len())? != key.digest {
+            return None;
+        }
+        bytecode.drain(..key.digest.len());
+
+        // not written by us
+        let min = LuauBytecodeTag::LBC_VERSION_MIN.0 as u8;
+        let max = LuauBytecodeTag::LBC_VERSION_MAX.0 as u8;
+        if !bytecode.first().is_some_and(|v| (min..=max).contains(v)) {
+            return None;
+        }
+
+        Some(Arc::from(bytecode))
+    }
+    fn insert(&mut self, key: CacheKey, bytecode: Arc<[u8]>) {
+        let path = self.path(&key);
+
+        // written to a temporary file first so that other processes never read half of it, with the same
+        // extension so that one left behind is removed by `prune` and `clear`
+        let temp = path.with_extension(format!("{}.tmp.{}", std::process::id(), Self::EXTENSION));
+        let contents = [&key.digest[..], &bytecode].concat();
+        if fs::write(&temp, contents).is_err() || fs::rename(&temp, &path).is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+    }
+}
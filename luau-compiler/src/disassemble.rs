@@ -0,0 +1,165 @@
+use crate::bytecode::{op_info, Bytecode, Constant, Instruction, Operands, Proto};
+use luau_sys::common::bytecode::LuauOpcode;
+use std::fmt::Write;
+
+impl Bytecode {
+    /// Human readable listing of all functions, their constants and instructions
+    ///
+    /// Instructions are shown with their position, line, operands and the auxiliary word in brackets,
+    /// followed by the constants, functions and jump targets they refer to.
+    pub fn disassemble(&self) -> String {
+        let mut out = String::new();
+
+        for (i, proto) in self.protos.iter().enumerate() {
+            if i != 0 {
+                out.push('\n');
+            }
+            // writing to a string doesn't fail
+            self.disassemble_proto(&mut out, i, proto).unwrap();
+        }
+
+        out
+    }
+    fn disassemble_proto(&self, out: &mut String, index: usize, proto: &Proto) -> std::fmt::Result {
+        write!(out, "function #{index} {}", self.proto_name(index))?;
+        if index == self.main {
+            write!(out, " (main)")?;
+        }
+        writeln!(
+            out,
+            " [line {}, params {}, upvalues {}, stack {}{}]",
+            proto.line_defined,
+            proto.num_params,
+            proto.num_upvalues,
+            proto.max_stack_size,
+            if proto.is_vararg { ", vararg" } else { "" }
+        )?;
+
+        if !proto.constants.is_empty() {
+            writeln!(out, "constants:")?;
+            for k in 0..proto.constants.len() {
+                writeln!(out, "  K{k} = {}", self.constant(proto, k as u32))?;
+            }
+        }
+
+        writeln!(out, "code:")?;
+        for (pc, insn) in proto.instructions() {
+            let line = match proto.line(pc) {
+                Some(line) => format!("[{line:>3}] "),
+                None => String::new(),
+            };
+
+            let Some(info) = op_info(insn.op()) else {
+                writeln!(out, "  {pc:>4} {line}<unknown opcode {}>", insn.op().0)?;
+                continue;
+            };
+
+            let mut operands = match info.operands {
+                Operands::None => String::new(),
+                Operands::A => format!("{}", insn.a()),
+                Operands::AB => format!("{} {}", insn.a(), insn.b()),
+                Operands::ABC => format!("{} {} {}", insn.a(), insn.b(), insn.c()),
+                Operands::AD => format!("{} {}", insn.a(), insn.d()),
+                Operands::E => format!("{}", insn.e()),
+            };
+            if let Some(aux) = insn.aux {
+                write!(operands, " [{aux}]")?;
+            }
+
+            write!(out, "  {pc:>4} {line}{:<16}{operands}", info.name)?;
+            if let Some(comment) = self.comment(proto, pc, &insn) {
+                write!(out, " ; {comment}")?;
+            }
+            writeln!(out)?;
+        }
+
+        Ok(())
+    }
+    /// Describes what the instruction refers to
+    fn comment(&self, proto: &Proto, pc: usize, insn: &Instruction) -> Option<String> {
+        let info = op_info(insn.op())?;
+        if info.jump {
+            let offset = match info.operands {
+                Operands::E => insn.e(),
+                _ => insn.d(),
+            };
+            let target = pc as i64 + 1 + offset as i64;
+
+            return Some(match insn.op() {
+                LuauOpcode::LOP_JUMPXEQKN | LuauOpcode::LOP_JUMPXEQKS => {
+                    format!("{} to {target}", self.constant(proto, insn.aux? & 0xffffff))
+                }
+                _ => format!("to {target}"),
+            });
+        }
+
+        let k = match insn.op() {
+            LuauOpcode::LOP_LOADK
+            | LuauOpcode::LOP_GETIMPORT
+            | LuauOpcode::LOP_DUPTABLE
+            | LuauOpcode::LOP_DUPCLOSURE => insn.d() as u32,
+            LuauOpcode::LOP_LOADKX
+            | LuauOpcode::LOP_GETGLOBAL
+            | LuauOpcode::LOP_SETGLOBAL
+            | LuauOpcode::LOP_GETTABLEKS
+            | LuauOpcode::LOP_SETTABLEKS
+            | LuauOpcode::LOP_NAMECALL
+            | LuauOpcode::LOP_FASTCALL2K => insn.aux?,
+            LuauOpcode::LOP_ADDK
+            | LuauOpcode::LOP_SUBK
+            | LuauOpcode::LOP_MULK
+            | LuauOpcode::LOP_DIVK
+            | LuauOpcode::LOP_MODK
+            | LuauOpcode::LOP_POWK
+            | LuauOpcode::LOP_ANDK
+            | LuauOpcode::LOP_ORK
+            | LuauOpcode::LOP_IDIVK => insn.c() as u32,
+            LuauOpcode::LOP_SUBRK | LuauOpcode::LOP_DIVRK => insn.b() as u32,
+            LuauOpcode::LOP_NEWCLOSURE => {
+                let child = *proto.children.get(insn.d() as usize)?;
+                return Some(format!("function #{child} {}", self.proto_name(child)));
+            }
+            _ => return None,
+        };
+
+        Some(format!("K{k} = {}", self.constant(proto, k)))
+    }
+    fn constant(&self, proto: &Proto, k: u32) -> String {
+        let Some(constant) = proto.constants.get(k as usize) else {
+            return format!("<constant {k} out of range>");
+        };
+
+        match constant {
+            Constant::Nil => "nil".to_owned(),
+            Constant::Boolean(b) => b.to_string(),
+            Constant::Number(n) => n.to_string(),
+            Constant::Vector([x, y, z, w]) => format!("vector({x}, {y}, {z}, {w})"),
+            Constant::String(s) => format!("\"{}\"", self.strings[*s].escape_ascii()),
+            Constant::Import(import) => {
+                let names: Vec<_> = import
+                    .path()
+                    .map(|k| self.constant_name(proto, k))
+                    .collect();
+                format!("import {}", names.join("."))
+            }
+            Constant::Table(keys) => {
+                let keys: Vec<_> = keys.iter().map(|&k| self.constant_name(proto, k)).collect();
+                format!("table {{{}}}", keys.join(", "))
+            }
+            Constant::Closure(f) => format!("function #{f} {}", self.proto_name(*f)),
+        }
+    }
+    /// A string constant without quotes, for imports and table keys
+    fn constant_name(&self, proto: &Proto, k: u32) -> String {
+        match proto.constants.get(k as usize) {
+            Some(Constant::String(s)) => self.strings[*s].escape_ascii().to_string(),
+            _ => format!("K{k}"),
+        }
+    }
+    fn proto_name(&self, index: usize) -> String {
+        match self.protos[index].debug_name {
+            Some(name) => String::from_utf8_lossy(&self.strings[name]).into_owned(),
+            None => "<anonymous>".to_owned(),
+        }
+    }
+}
@@ -7,15 +7,95 @@ use std::{
 
 pub struct CompileError {
     pub(crate) buffer: Malloced<[u8]>,
+    pub(crate) line: Option<usize>,
+    /// the line of source code that failed to compile
+    pub(crate) text: Option<String>,
+    /// where the message starts in the buffer, after the line
+    pub(crate) message_start: usize,
 }
 
 impl CompileError {
+    /// Parses the `"\0:<line>: <message>"` buffer returned by `luau_compile`
+    pub(crate) fn new(buffer: Malloced<[u8]>, source: &str) -> Self {
+        let rest = &buffer[2..];
+        let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+
+        let line = str::from_utf8(&rest[..digits])
+            .ok()
+            .and_then(|l| l.parse().ok())
+            .filter(|_| rest[digits..].starts_with(b": "));
+
+        Self {
+            message_start: if line.is_some() { 2 + digits + 2 } else { 2 },
+            buffer,
+            text: line
+                .and_then(|line| source_line(source, line))
+                .map(str::to_owned),
+            line,
+        }
+    }
+    /// The error message, without the location
     pub fn message(&self) -> &str {
-        match str::from_utf8(&self.buffer[2..]) {
+        match str::from_utf8(&self.buffer[self.message_start..]) {
             Ok(s) => s,
             Err(e) => panic!("Compile error not valid utf8: {e}"),
         }
     }
+    /// Line of the error, counted from 1
+    ///
+    /// The luau compiler only reports lines, not columns.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+    /// The line of source code that the error refers to, without the line break
+    pub fn line_text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+    /// Renders the error with the line of source code it refers to, underlined with carets
+    ///
+    /// Also used by the alternate `Display` format (`{:#}`).
+    pub fn snippet(&self) -> Snippet<'_> {
+        Snippet { error: self }
+    }
+}
+
+/// The line of the source code, counted from 1
+fn source_line(source: &str, line: usize) -> Option<&str> {
+    // an error at the end of source code that ends with a newline is on the empty last line
+    source
+        .split('\n')
+        .nth(line.checked_sub(1)?)
+        .map(|l| l.strip_suffix('\r').unwrap_or(l))
+}
+
+/// A [`CompileError`] with the line of source code it refers to, returned by [`CompileError::snippet`]
+pub struct Snippet<'e> {
+    error: &'e CompileError,
+}
+
+impl Display for Snippet<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let error = self.error;
+        write!(f, "luau compile error: {}", error.message())?;
+
+        let (Some(line), Some(text)) = (error.line, error.line_text()) else {
+            return Ok(());
+        };
+
+        let number = line.to_string();
+        let pad = " ".repeat(number.len());
+
+        // the whole line is underlined, without the indentation
+        let code = text.trim();
+        let before = text[..text.len() - text.trim_start().len()].chars().count();
+        let under = code.chars().count().max(1);
+
+        writeln!(f)?;
+        writeln!(f, "{pad}--> line {line}")?;
+        writeln!(f, "{pad} |")?;
+        writeln!(f, "{number} | {text}")?;
+        write!(f, "{pad} | {}{}", " ".repeat(before), "^".repeat(under))
+    }
 }
 
 impl Debug for CompileError {
@@ -26,7 +106,14 @@ impl Debug for CompileError {
 
 impl Display for CompileError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "luau compile error: {}", self.message())
+        if f.alternate() {
+            return Display::fmt(&self.snippet(), f);
+        }
+
+        match self.line {
+            Some(line) => write!(f, "luau compile error: {line}: {}", self.message()),
+            None => write!(f, "luau compile error: {}", self.message()),
+        }
     }
 }
 
@@ -15,10 +15,16 @@ use std::{
     ptr::null,
 };
 
+pub mod bytecode;
+pub mod cache;
+mod disassemble;
 mod error;
 mod options;
+mod verify;
 
-pub use error::CompileError;
+pub use bytecode::{Bytecode, BytecodeError};
+pub use cache::CompileCache;
+pub use error::{CompileError, Snippet};
 pub use options::*;
 
 pub fn compile(code: &str, opts: &CompilerOptions) -> Result<Malloced<[u8]>, CompileError> {
@@ -198,7 +204,7 @@ pub fn compile(code: &str, opts: &CompilerOptions) -> Result<Malloced<[u8]>, Com
             "bytecode compile result error must start with `:`"
         );
 
-        return Err(CompileError { buffer });
+        return Err(CompileError::new(buffer, code));
     }
 
     Ok(buffer)
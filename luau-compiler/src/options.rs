@@ -1,7 +1,15 @@
 use luau_sys::common::bytecode::LuauBytecodeType;
-use std::{collections::HashMap, ffi::CString};
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    hash::{Hash, Hasher},
+};
 
-#[derive(Debug, Clone, PartialEq)]
+/// Options of [`compile`](crate::compile)
+///
+/// `Hash` gives the same hashes on every run when the hasher does, the libraries' maps are hashed in the order
+/// of their keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CompilerOptions {
     pub optimization: OptLevel,
     pub debug: DebugLevel,
@@ -31,6 +39,8 @@ impl CompilerOptions {
             disabled_builtins: Vec::new(),
         }
     }
+    /// Makes calls to `vector_lib.vector_constructor` build vectors like `vector.create` does, with
+    /// `vector_type` as their type name in type annotations
     pub fn set_alt_vector(
         &mut self,
         vector_lib: impl AsRef<str>,
@@ -56,6 +66,11 @@ impl CompilerOptions {
 
         self
     }
+    /// Globals declared with [`set_mutable_globals`](Self::set_mutable_globals)
+    pub fn mutable_globals(&self) -> impl Iterator<Item = &str> {
+        // created from strings
+        self.mutable_globals.iter().map(|g| g.to_str().unwrap())
+    }
     pub fn set_userdata_types(
         &mut self,
         userdata_types: impl IntoIterator<Item = impl AsRef<str>>,
@@ -85,6 +100,73 @@ impl CompilerOptions {
     }
 }
 
+impl CompilerOptions {
+    /// Feeds every option to the hasher in the same order on every run, maps are sorted by key first
+    pub(crate) fn stable_hash(&self, state: &mut impl Hasher) {
+        fn strings(state: &mut impl Hasher, strings: &[CString]) {
+            state.write_usize(strings.len());
+            for s in strings {
+                state.write(s.as_bytes_with_nul());
+            }
+        }
+
+        state.write_u8(self.optimization as u8);
+        state.write_u8(self.debug as u8);
+        state.write_u8(self.generate_type_info_for_all as u8);
+        state.write_u8(self.coverage as u8);
+
+        match &self.alt_vector {
+            Some(vector) => {
+                state.write_u8(1);
+                state.write(vector.library_name.as_bytes_with_nul());
+                state.write(vector.constructor.as_bytes_with_nul());
+                state.write(vector.type_name.as_bytes_with_nul());
+            }
+            None => state.write_u8(0),
+        }
+        strings(state, &self.mutable_globals);
+        strings(state, &self.userdata_types);
+        strings(state, &self.disabled_builtins);
+
+        state.write_usize(self.known_libraries.len());
+        for library in &self.known_libraries {
+            state.write(library.name.as_bytes_with_nul());
+            hash_members(state, &library.types, &library.constants);
+        }
+    }
+}
+
+impl Hash for CompilerOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.stable_hash(state);
+    }
+}
+
+/// Hashes the members of a library sorted by name
+fn hash_members(
+    state: &mut impl Hasher,
+    types: &HashMap<String, LuauBytecodeType>,
+    constants: &HashMap<String, Constant>,
+) {
+    let mut types: Vec<_> = types.iter().collect();
+    types.sort_by_key(|(name, _)| *name);
+    state.write_usize(types.len());
+    for (name, ty) in types {
+        state.write(name.as_bytes());
+        state.write_u8(0xff);
+        state.write_u32(ty.0 as u32);
+    }
+
+    let mut constants: Vec<_> = constants.iter().collect();
+    constants.sort_by_key(|(name, _)| *name);
+    state.write_usize(constants.len());
+    for (name, constant) in constants {
+        state.write(name.as_bytes());
+        state.write_u8(0xff);
+        constant.stable_hash(state);
+    }
+}
+
 impl Default for CompilerOptions {
     fn default() -> Self {
         Self::new()
@@ -94,7 +176,12 @@ impl Default for CompilerOptions {
 /// 0 - no optimization
 /// 1 - baseline optimization level that doesn't prevent debuggability
 /// 2 - includes optimizations that harm debuggability such as inlining
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum OptLevel {
     None = 0,
     Baseline = 1,
@@ -109,7 +196,12 @@ impl Default for OptLevel {
 /// 0 - no debugging support
 /// 1 - line info & function names only; sufficient for backtraces
 /// 2 - full debug info with local & upvalue names; necessary for debugger
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum DebugLevel {
     NoDebug = 0,
     Backtrace = 1,
@@ -124,7 +216,12 @@ impl Default for DebugLevel {
 /// 0 - no code coverage support
 /// 1 - statement coverage
 /// 2 - statement and expression coverage (verbose)
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum CoverageLevel {
     NoCoverage = 0,
     Statement = 1,
@@ -136,20 +233,20 @@ impl Default for CoverageLevel {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct VectorOptions {
     pub(crate) library_name: CString,
     pub(crate) constructor: CString,
     pub(crate) type_name: CString,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct LibraryWithKnownMembersC {
     pub(crate) name: CString,
     pub(crate) types: HashMap<String, LuauBytecodeType>,
     pub(crate) constants: HashMap<String, Constant>,
 }
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LibraryWithKnownMembers {
     pub name: String,
     pub types: HashMap<String, LuauBytecodeType>,
@@ -164,6 +261,13 @@ impl LibraryWithKnownMembers {
         }
     }
 }
+impl Hash for LibraryWithKnownMembers {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write(self.name.as_bytes());
+        state.write_u8(0xff);
+        hash_members(state, &self.types, &self.constants);
+    }
+}
 impl From<LibraryWithKnownMembers> for LibraryWithKnownMembersC {
     fn from(value: LibraryWithKnownMembers) -> Self {
         Self {
@@ -174,7 +278,14 @@ impl From<LibraryWithKnownMembers> for LibraryWithKnownMembersC {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Numbers are compared and hashed by their bits, so `NaN` equals itself and `-0.0` doesn't equal `0.0`,
+/// they are different constants to the compiler
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Constant {
     Nil,
     Bool(bool),
@@ -182,3 +293,274 @@ pub enum Constant {
     Vector(f32, f32, f32, f32),
     String(String),
 }
+
+impl PartialEq for Constant {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Constant::Nil, Constant::Nil) => true,
+            (Constant::Bool(a), Constant::Bool(b)) => a == b,
+            (Constant::Number(a), Constant::Number(b)) => a.to_bits() == b.to_bits(),
+            (Constant::Vector(x1, y1, z1, w1), Constant::Vector(x2, y2, z2, w2)) => {
+                [x1, y1, z1, w1]
+                    .iter()
+                    .zip([x2, y2, z2, w2])
+                    .all(|(a, b)| a.to_bits() == b.to_bits())
+            }
+            (Constant::String(a), Constant::String(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+impl Eq for Constant {}
+impl Hash for Constant {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.stable_hash(state);
+    }
+}
+impl Constant {
+    fn stable_hash(&self, state: &mut impl Hasher) {
+        match self {
+            Constant::Nil => state.write_u8(0),
+            Constant::Bool(b) => {
+                state.write_u8(1);
+                state.write_u8(*b as u8);
+            }
+            Constant::Number(n) => {
+                state.write_u8(2);
+                state.write_u64(n.to_bits());
+            }
+            Constant::Vector(x, y, z, w) => {
+                state.write_u8(3);
+                for c in [x, y, z, w] {
+                    state.write_u32(c.to_bits());
+                }
+            }
+            Constant::String(s) => {
+                state.write_u8(4);
+                state.write_usize(s.len());
+                state.write(s.as_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::*;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+    use std::{collections::BTreeMap, fmt};
+
+    /// [`CompilerOptions`] with strings instead of C strings, maps sorted by key and missing fields defaulted
+    #[derive(Serialize, Deserialize, Default)]
+    #[serde(default, deny_unknown_fields)]
+    struct Options {
+        optimization: OptLevel,
+        debug: DebugLevel,
+        generate_type_info_for_all: bool,
+        coverage: CoverageLevel,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        alt_vector: Option<AltVector>,
+        mutable_globals: Vec<String>,
+        userdata_types: Vec<String>,
+        known_libraries: Vec<Library>,
+        disabled_builtins: Vec<String>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct AltVector {
+        library: String,
+        constructor: String,
+        type_name: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Library {
+        name: String,
+        #[serde(default)]
+        types: BTreeMap<String, Type>,
+        #[serde(default)]
+        constants: BTreeMap<String, Constant>,
+    }
+
+    /// Written as its name in luau (`"number"`, `"string?"`), or its number when it has none
+    struct Type(LuauBytecodeType);
+
+    const TYPE_NAMES: [(LuauBytecodeType, &str); 11] = [
+        (LuauBytecodeType::LBC_TYPE_NIL, "nil"),
+        (LuauBytecodeType::LBC_TYPE_BOOLEAN, "boolean"),
+        (LuauBytecodeType::LBC_TYPE_NUMBER, "number"),
+        (LuauBytecodeType::LBC_TYPE_STRING, "string"),
+        (LuauBytecodeType::LBC_TYPE_TABLE, "table"),
+        (LuauBytecodeType::LBC_TYPE_FUNCTION, "function"),
+        (LuauBytecodeType::LBC_TYPE_THREAD, "thread"),
+        (LuauBytecodeType::LBC_TYPE_USERDATA, "userdata"),
+        (LuauBytecodeType::LBC_TYPE_VECTOR, "vector"),
+        (LuauBytecodeType::LBC_TYPE_BUFFER, "buffer"),
+        (LuauBytecodeType::LBC_TYPE_ANY, "any"),
+    ];
+
+    impl Serialize for Type {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let optional = LuauBytecodeType::LBC_TYPE_OPTIONAL_BIT.0;
+            let base = self.0 .0 & !optional;
+
+            match TYPE_NAMES.iter().find(|(ty, _)| ty.0 == base) {
+                Some((_, name)) if self.0 .0 & optional != 0 => {
+                    serializer.serialize_str(&format!("{name}?"))
+                }
+                Some((_, name)) => serializer.serialize_str(name),
+                None => serializer.serialize_u64(self.0 .0 as u64),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Type {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct Visitor;
+
+            impl de::Visitor<'_> for Visitor {
+                type Value = Type;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    write!(
+                        f,
+                        "a luau type name like \"number\" or \"string?\", or a type number"
+                    )
+                }
+                fn visit_str<E: de::Error>(self, v: &str) -> Result<Type, E> {
+                    let (name, optional) = match v.strip_suffix('?') {
+                        Some(name) => (name, LuauBytecodeType::LBC_TYPE_OPTIONAL_BIT.0),
+                        None => (v, 0),
+                    };
+
+                    match TYPE_NAMES.iter().find(|(_, n)| *n == name) {
+                        Some((ty, _)) => Ok(Type(LuauBytecodeType(ty.0 | optional))),
+                        None => Err(E::invalid_value(de::Unexpected::Str(v), &self)),
+                    }
+                }
+                fn visit_u64<E: de::Error>(self, v: u64) -> Result<Type, E> {
+                    match v.try_into() {
+                        Ok(v) => Ok(Type(LuauBytecodeType(v))),
+                        Err(_) => Err(E::invalid_value(de::Unexpected::Unsigned(v), &self)),
+                    }
+                }
+                fn visit_i64<E: de::Error>(self, v: i64) -> Result<Type, E> {
+                    match v.try_into() {
+                        Ok(v) => Ok(Type(LuauBytecodeType(v))),
+                        Err(_) => Err(E::invalid_value(de::Unexpected::Signed(v), &self)),
+                    }
+                }
+            }
+
+            deserializer.deserialize_any(Visitor)
+        }
+    }
+
+    fn string(s: &CString) -> String {
+        // all of them are created from strings
+        s.to_str().unwrap().to_owned()
+    }
+    fn c_string<E: de::Error>(s: String) -> Result<CString, E> {
+        CString::new(s).map_err(|e| E::custom(format!("string contains a null byte: {e}")))
+    }
+
+    impl Serialize for CompilerOptions {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Options {
+                optimization: self.optimization,
+                debug: self.debug,
+                generate_type_info_for_all: self.generate_type_info_for_all,
+                coverage: self.coverage,
+                alt_vector: self.alt_vector.as_ref().map(|v| AltVector {
+                    library: string(&v.library_name),
+                    constructor: string(&v.constructor),
+                    type_name: string(&v.type_name),
+                }),
+                mutable_globals: self.mutable_globals.iter().map(string).collect(),
+                userdata_types: self.userdata_types.iter().map(string).collect(),
+                known_libraries: self
+                    .known_libraries
+                    .iter()
+                    .map(|l| Library {
+                        name: string(&l.name),
+                        types: l.types.iter().map(|(k, v)| (k.clone(), Type(*v))).collect(),
+                        constants: l.constants.clone().into_iter().collect(),
+                    })
+                    .collect(),
+                disabled_builtins: self.disabled_builtins.iter().map(string).collect(),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for CompilerOptions {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let options = Options::deserialize(deserializer)?;
+
+            let strings = |strings: Vec<String>| {
+                strings
+                    .into_iter()
+                    .map(c_string)
+                    .collect::<Result<Vec<_>, D::Error>>()
+            };
+
+            Ok(CompilerOptions {
+                optimization: options.optimization,
+                debug: options.debug,
+                generate_type_info_for_all: options.generate_type_info_for_all,
+                coverage: options.coverage,
+                alt_vector: match options.alt_vector {
+                    Some(v) => Some(VectorOptions {
+                        library_name: c_string(v.library)?,
+                        constructor: c_string(v.constructor)?,
+                        type_name: c_string(v.type_name)?,
+                    }),
+                    None => None,
+                },
+                mutable_globals: strings(options.mutable_globals)?,
+                userdata_types: strings(options.userdata_types)?,
+                known_libraries: options
+                    .known_libraries
+                    .into_iter()
+                    .map(|l| {
+                        Ok(LibraryWithKnownMembersC {
+                            name: c_string(l.name)?,
+                            types: l.types.into_iter().map(|(k, v)| (k, v.0)).collect(),
+                            constants: l.constants.into_iter().collect(),
+                        })
+                    })
+                    .collect::<Result<_, D::Error>>()?,
+                disabled_builtins: strings(options.disabled_builtins)?,
+            })
+        }
+    }
+
+    impl Serialize for LibraryWithKnownMembers {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Library {
+                name: self.name.clone(),
+                types: self
+                    .types
+                    .iter()
+                    .map(|(k, v)| (k.clone(), Type(*v)))
+                    .collect(),
+                constants: self.constants.clone().into_iter().collect(),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for LibraryWithKnownMembers {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let library = Library::deserialize(deserializer)?;
+
+            Ok(LibraryWithKnownMembers {
+                name: library.name,
+                types: library.types.into_iter().map(|(k, v)| (k, v.0)).collect(),
+                constants: library.constants.into_iter().collect(),
+            })
+        }
+    }
+}
@@ -0,0 +1,815 @@
+use crate::bytecode::{
+    invalid, op_info, Bytecode, BytecodeError, Constant, Import, Instruction, Proto, Reader,
+};
+use luau_sys::{
+    common::bytecode::{LuauBytecodeType, LuauOpcode},
+    shim,
+};
+use std::ffi::c_int;
+
+/// Capture types of `CAPTURE` instructions, `LCT_*` in `Bytecode.h`
+const CAPTURE_VAL: u8 = 0;
+const CAPTURE_REF: u8 = 1;
+const CAPTURE_UPVAL: u8 = 2;
+
+impl Bytecode {
+    /// Checks that the VM can run the bytecode without accessing anything out of bounds
+    ///
+    /// `luau_load` and the VM trust the bytecode completely, so bytecode from untrusted sources must pass this
+    /// before being loaded. Every operand that the VM uses as an index is checked: registers against the stack
+    /// size, constants (and their types where the VM assumes them), upvalues, functions, builtins and jump
+    /// targets. So are the instruction sequences that the VM expects, like the captures after a closure and the
+    /// call after a fastcall, and the type and debug information. Generic `for` loops must only be entered
+    /// through their `FORGPREP`, without the iteration state being overwritten in between, since the VM trusts
+    /// it.
+    ///
+    /// What the code does at runtime is not checked, it may still raise errors or loop forever.
+    pub fn verify(&self) -> Result<(), BytecodeError> {
+        // the main function is created without upvalues
+        if self.protos[self.main].num_upvalues != 0 {
+            return Err(invalid("main function has upvalues"));
+        }
+
+        for (index, proto) in self.protos.iter().enumerate() {
+            Verifier {
+                bytecode: self,
+                proto,
+            }
+            .verify()
+            .map_err(|e| invalid(format!("function #{index}: {e}")))?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Verifier<'b> {
+    bytecode: &'b Bytecode,
+    proto: &'b Proto,
+}
+
+impl Verifier<'_> {
+    fn verify(&self) -> Result<(), String> {
+        let p = self.proto;
+
+        if p.num_params > p.max_stack_size {
+            return Err("more parameters than stack slots".to_owned());
+        }
+        if let Some(debug_info) = &p.debug_info {
+            if debug_info.upvalues.len() > p.num_upvalues as usize {
+                return Err("more upvalue names than upvalues".to_owned());
+            }
+            for local in &debug_info.locals {
+                self.registers(local.register as u32, 1)?;
+            }
+        }
+
+        self.verify_constants()?;
+        self.verify_type_info()?;
+        self.verify_code()
+    }
+    fn verify_constants(&self) -> Result<(), String> {
+        for (k, constant) in self.proto.constants.iter().enumerate() {
+            // resolved while loading, so they can only use the constants before them
+            match constant {
+                Constant::Import(import) => self.import_path(*import, k)?,
+                Constant::Table(keys) => {
+                    for &key in keys {
+                        self.string_constant(key, k)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+    fn verify_type_info(&self) -> Result<(), String> {
+        let p = self.proto;
+        if p.type_info.is_empty() {
+            return Ok(());
+        }
+
+        let function_type = |types: &[u8]| {
+            if types.len() != 2 + p.num_params as usize
+                || types[0] != LuauBytecodeType::LBC_TYPE_FUNCTION.0 as u8
+                || types[1] != p.num_params
+            {
+                return Err("function type doesn't match the parameters".to_owned());
+            }
+
+            Ok(())
+        };
+
+        if self.bytecode.types_version == 1 {
+            return function_type(&p.type_info);
+        }
+
+        let mut r = Reader {
+            data: &p.type_info,
+            offset: 0,
+        };
+        let malformed = |_: BytecodeError| "malformed type information".to_owned();
+
+        let type_size = r.varint().map_err(malformed)?;
+        let upvalue_count = r.varint().map_err(malformed)?;
+        let local_count = r.varint().map_err(malformed)?;
+
+        if type_size != 0 {
+            function_type(r.bytes(type_size as usize).map_err(malformed)?)?;
+        }
+        if upvalue_count != 0 {
+            if upvalue_count != p.num_upvalues as u32 {
+                return Err("upvalue types don't match the upvalues".to_owned());
+            }
+            r.bytes(upvalue_count as usize).map_err(malformed)?;
+        }
+        for _ in 0..local_count {
+            let _ty = r.u8().map_err(malformed)?;
+            self.registers(r.u8().map_err(malformed)? as u32, 1)?;
+            let _start_pc = r.varint().map_err(malformed)?;
+            let _len = r.varint().map_err(malformed)?;
+        }
+
+        if r.offset != p.type_info.len() {
+            return Err("trailing type information".to_owned());
+        }
+
+        Ok(())
+    }
+    fn verify_code(&self) -> Result<(), String> {
+        let p = self.proto;
+        let insns: Vec<_> = p.instructions().collect();
+
+        // instructions by their position, `None` for auxiliary words
+        let mut at = vec![None; p.code.len()];
+        for &(pc, insn) in &insns {
+            at[pc] = Some(insn);
+        }
+
+        let mut state = CodeState {
+            at,
+            captures: vec![false; p.code.len()],
+            jumps: Vec::new(),
+        };
+
+        for (i, &(pc, insn)) in insns.iter().enumerate() {
+            self.verify_instruction(&mut state, &insns[i + 1..], pc, insn)
+                .map_err(|e| format!("instruction {pc}: {e}"))?;
+        }
+
+        // execution can't continue past the end
+        match insns.last() {
+            Some((_, insn))
+                if matches!(
+                    insn.op(),
+                    LuauOpcode::LOP_RETURN
+                        | LuauOpcode::LOP_JUMP
+                        | LuauOpcode::LOP_JUMPBACK
+                        | LuauOpcode::LOP_JUMPX
+                ) => {}
+            _ => return Err("code doesn't end with a return or a jump".to_owned()),
+        }
+
+        for (pc, target) in state.jumps {
+            let valid = usize::try_from(target)
+                .ok()
+                .filter(|&t| t < p.code.len() && state.at[t].is_some() && !state.captures[t]);
+
+            if valid.is_none() {
+                return Err(format!("instruction {pc}: invalid jump target {target}"));
+            }
+        }
+
+        self.verify_generic_loops(&insns, &state.at)
+    }
+    /// `FORGLOOP` must only be reached with the iteration state that a `FORGPREP` with the same registers left
+    ///
+    /// The VM reads the table and the position in it from the registers without checking their types. So every
+    /// path through the code is followed, keeping track of which loops still have their state intact, and of
+    /// the registers captured by reference, which closures can write to at any time.
+    fn verify_generic_loops(
+        &self,
+        insns: &[(usize, Instruction)],
+        at: &[Option<Instruction>],
+    ) -> Result<(), String> {
+        if !insns
+            .iter()
+            .any(|(_, insn)| insn.op() == LuauOpcode::LOP_FORGLOOP)
+        {
+            return Ok(());
+        }
+
+        // position in `insns` of each instruction
+        let mut index = vec![usize::MAX; at.len()];
+        for (i, &(pc, _)) in insns.iter().enumerate() {
+            index[pc] = i;
+        }
+
+        let mut states: Vec<Option<LoopState>> = vec![None; insns.len()];
+        states[0] = Some(LoopState::default());
+        let mut pending = vec![0];
+
+        while let Some(i) = pending.pop() {
+            let (pc, insn) = insns[i];
+            let mut state = states[i].clone().unwrap();
+            let (a, c, aux) = (insn.a() as u32, insn.c() as u32, insn.aux.unwrap_or(0));
+            let next = insns.get(i + 1).map(|&(pc, _)| pc);
+            let target = |offset: i32| (pc as i64 + 1 + offset as i64) as usize;
+
+            // where execution continues, and the state there
+            let mut edges = Vec::new();
+
+            match insn.op() {
+                LuauOpcode::LOP_RETURN => {}
+                LuauOpcode::LOP_JUMP | LuauOpcode::LOP_JUMPBACK => edges.push(target(insn.d())),
+                LuauOpcode::LOP_JUMPX => edges.push(target(insn.e())),
+                LuauOpcode::LOP_LOADB => {
+                    state.write(a, 1);
+                    edges.push(target(c as i32));
+                }
+                LuauOpcode::LOP_FORGPREP
+                | LuauOpcode::LOP_FORGPREP_INEXT
+                | LuauOpcode::LOP_FORGPREP_NEXT => {
+                    state.write(a, 3);
+                    if !(a..a + 3).any(|r| state.captured.contains(r)) {
+                        state.loops.insert(a);
+                    }
+                    edges.push(target(insn.d()));
+                }
+                LuauOpcode::LOP_FORGLOOP => {
+                    if !state.loops.contains(a) {
+                        return Err(format!(
+                            "instruction {pc}: generic for loop not prepared by a FORGPREP"
+                        ));
+                    }
+                    // the loop updates its own position, the variables come after it
+                    state.write(a, 3 + (aux & 0xff));
+                    state.loops.insert(a);
+                    edges.push(target(insn.d()));
+                    edges.extend(next);
+                }
+                LuauOpcode::LOP_CAPTURE => {
+                    if insn.a() == CAPTURE_REF {
+                        let r = insn.b() as u32;
+                        state.captured.insert(r);
+                        state.write(r, 1);
+                    }
+                    edges.extend(next);
+                }
+                LuauOpcode::LOP_CLOSEUPVALS => {
+                    state.captured.remove_from(a);
+                    edges.extend(next);
+                }
+                LuauOpcode::LOP_FASTCALL
+                | LuauOpcode::LOP_FASTCALL1
+                | LuauOpcode::LOP_FASTCALL2
+                | LuauOpcode::LOP_FASTCALL2K
+                | LuauOpcode::LOP_FASTCALL3 => {
+                    edges.extend(next);
+
+                    // on success the results are stored like the call would, which is skipped
+                    let call = pc + 1 + c as usize;
+                    let mut success = state.clone();
+                    let (start, count) = writes(at[call].unwrap());
+                    success.write(start, count);
+                    self.merge(&mut states, &mut pending, index[call + 1], success);
+                }
+                op => {
+                    let (start, count) = writes(insn);
+                    state.write(start, count);
+
+                    let conditional = matches!(
+                        op,
+                        LuauOpcode::LOP_JUMPIF
+                            | LuauOpcode::LOP_JUMPIFNOT
+                            | LuauOpcode::LOP_JUMPIFEQ
+                            | LuauOpcode::LOP_JUMPIFLE
+                            | LuauOpcode::LOP_JUMPIFLT
+                            | LuauOpcode::LOP_JUMPIFNOTEQ
+                            | LuauOpcode::LOP_JUMPIFNOTLE
+                            | LuauOpcode::LOP_JUMPIFNOTLT
+                            | LuauOpcode::LOP_JUMPXEQKNIL
+                            | LuauOpcode::LOP_JUMPXEQKB
+                            | LuauOpcode::LOP_JUMPXEQKN
+                            | LuauOpcode::LOP_JUMPXEQKS
+                            | LuauOpcode::LOP_FORNPREP
+                            | LuauOpcode::LOP_FORNLOOP
+                    );
+                    if conditional {
+                        edges.push(target(insn.d()));
+                    }
+                    edges.extend(next);
+                }
+            }
+
+            for pc in edges {
+                self.merge(&mut states, &mut pending, index[pc], state.clone());
+            }
+        }
+
+        Ok(())
+    }
+    /// Combines the state at the instruction with the state of another path leading to it
+    fn merge(
+        &self,
+        states: &mut [Option<LoopState>],
+        pending: &mut Vec<usize>,
+        i: usize,
+        state: LoopState,
+    ) {
+        let merged = match &states[i] {
+            None => state,
+            Some(old) => LoopState {
+                loops: old.loops.intersection(&state.loops),
+                captured: old.captured.union(&state.captured),
+            },
+        };
+
+        if states[i].as_ref() != Some(&merged) {
+            states[i] = Some(merged);
+            pending.push(i);
+        }
+    }
+    fn verify_instruction(
+        &self,
+        state: &mut CodeState,
+        following: &[(usize, Instruction)],
+        pc: usize,
+        insn: Instruction,
+    ) -> Result<(), String> {
+        let p = self.proto;
+
+        let info = op_info(insn.op()).ok_or("unknown opcode")?;
+        let aux = match (info.aux, insn.aux) {
+            (true, None) => return Err("missing auxiliary word".to_owned()),
+            (_, aux) => aux.unwrap_or(0),
+        };
+        let (a, b, c, d) = (insn.a() as u32, insn.b() as u32, insn.c() as u32, insn.d());
+
+        match insn.op() {
+            LuauOpcode::LOP_NOP | LuauOpcode::LOP_COVERAGE => {}
+            LuauOpcode::LOP_BREAK => return Err("breakpoints can't be loaded".to_owned()),
+            LuauOpcode::LOP_NATIVECALL => {
+                return Err("native calls can't be loaded".to_owned());
+            }
+            LuauOpcode::LOP_LOADNIL
+            | LuauOpcode::LOP_LOADN
+            | LuauOpcode::LOP_CLOSEUPVALS
+            | LuauOpcode::LOP_NEWTABLE => self.registers(a, 1)?,
+            LuauOpcode::LOP_LOADB => {
+                self.registers(a, 1)?;
+                state.jump(pc, c as i32);
+            }
+            LuauOpcode::LOP_LOADK => {
+                self.registers(a, 1)?;
+                self.constant(d as u32)?;
+            }
+            LuauOpcode::LOP_LOADKX => {
+                self.registers(a, 1)?;
+                self.constant(aux)?;
+            }
+            LuauOpcode::LOP_MOVE
+            | LuauOpcode::LOP_NOT
+            | LuauOpcode::LOP_MINUS
+            | LuauOpcode::LOP_LENGTH => {
+                self.registers(a, 1)?;
+                self.registers(b, 1)?;
+            }
+            LuauOpcode::LOP_GETGLOBAL | LuauOpcode::LOP_SETGLOBAL => {
+                self.registers(a, 1)?;
+                self.string_constant(aux, p.constants.len())?;
+            }
+            LuauOpcode::LOP_GETUPVAL | LuauOpcode::LOP_SETUPVAL => {
+                self.registers(a, 1)?;
+                self.upvalue(b)?;
+            }
+            LuauOpcode::LOP_GETIMPORT => {
+                self.registers(a, 1)?;
+                match self.constant(d as u32)? {
+                    Constant::Import(_) => {}
+                    _ => return Err("import of a constant that is not an import".to_owned()),
+                }
+                // resolved again if the import was nil when loading
+                self.import_path(Import(aux), p.constants.len())?;
+            }
+            LuauOpcode::LOP_GETTABLE
+            | LuauOpcode::LOP_SETTABLE
+            | LuauOpcode::LOP_ADD
+            | LuauOpcode::LOP_SUB
+            | LuauOpcode::LOP_MUL
+            | LuauOpcode::LOP_DIV
+            | LuauOpcode::LOP_IDIV
+            | LuauOpcode::LOP_MOD
+            | LuauOpcode::LOP_POW
+            | LuauOpcode::LOP_AND
+            | LuauOpcode::LOP_OR => {
+                self.registers(a, 1)?;
+                self.registers(b, 1)?;
+                self.registers(c, 1)?;
+            }
+            LuauOpcode::LOP_GETTABLEKS | LuauOpcode::LOP_SETTABLEKS => {
+                self.registers(a, 1)?;
+                self.registers(b, 1)?;
+                self.string_constant(aux, p.constants.len())?;
+            }
+            LuauOpcode::LOP_GETTABLEN | LuauOpcode::LOP_SETTABLEN => {
+                self.registers(a, 1)?;
+                self.registers(b, 1)?;
+            }
+            LuauOpcode::LOP_NEWCLOSURE => {
+                self.registers(a, 1)?;
+                let child = *p
+                    .children
+                    .get(d as usize)
+                    .ok_or("child function out of range")?;
+                self.captures(state, following, child, false)?;
+            }
+            LuauOpcode::LOP_DUPCLOSURE => {
+                self.registers(a, 1)?;
+                match self.constant(d as u32)? {
+                    Constant::Closure(f) => self.captures(state, following, *f, true)?,
+                    _ => return Err("closure constant expected".to_owned()),
+                }
+            }
+            LuauOpcode::LOP_CAPTURE => {
+                if !state.captures[pc] {
+                    return Err("capture outside of a closure".to_owned());
+                }
+            }
+            LuauOpcode::LOP_NAMECALL => {
+                // the function and self
+                self.registers(a, 2)?;
+                self.registers(b, 1)?;
+                self.string_constant(aux, p.constants.len())?;
+
+                match following.first() {
+                    Some((_, next)) if next.op() == LuauOpcode::LOP_CALL => {}
+                    _ => return Err("namecall not followed by a call".to_owned()),
+                }
+            }
+            LuauOpcode::LOP_CALL => {
+                // the function, the arguments and the results
+                self.registers(a, b.max(1))?;
+                self.registers(a, c.saturating_sub(1))?;
+            }
+            // the count is stored plus one, 0 means up to the top of the stack
+            LuauOpcode::LOP_RETURN => self.registers(a, b.saturating_sub(1))?,
+            LuauOpcode::LOP_GETVARARGS => {
+                if !p.is_vararg {
+                    return Err("varargs in a function that doesn't take them".to_owned());
+                }
+                self.registers(a, b.saturating_sub(1))?;
+            }
+            LuauOpcode::LOP_JUMP | LuauOpcode::LOP_JUMPBACK => state.jump(pc, d),
+            LuauOpcode::LOP_JUMPX => state.jump(pc, insn.e()),
+            LuauOpcode::LOP_JUMPIF
+            | LuauOpcode::LOP_JUMPIFNOT
+            | LuauOpcode::LOP_JUMPXEQKNIL
+            | LuauOpcode::LOP_JUMPXEQKB => {
+                self.registers(a, 1)?;
+                state.jump(pc, d);
+            }
+            LuauOpcode::LOP_JUMPIFEQ
+            | LuauOpcode::LOP_JUMPIFLE
+            | LuauOpcode::LOP_JUMPIFLT
+            | LuauOpcode::LOP_JUMPIFNOTEQ
+            | LuauOpcode::LOP_JUMPIFNOTLE
+            | LuauOpcode::LOP_JUMPIFNOTLT => {
+                self.registers(a, 1)?;
+                self.registers(aux, 1)?;
+                state.jump(pc, d);
+            }
+            LuauOpcode::LOP_JUMPXEQKN => {
+                self.registers(a, 1)?;
+                self.number_constant(aux & 0xffffff)?;
+                state.jump(pc, d);
+            }
+            LuauOpcode::LOP_JUMPXEQKS => {
+                self.registers(a, 1)?;
+                self.string_constant(aux & 0xffffff, p.constants.len())?;
+                state.jump(pc, d);
+            }
+            LuauOpcode::LOP_ADDK
+            | LuauOpcode::LOP_SUBK
+            | LuauOpcode::LOP_MULK
+            | LuauOpcode::LOP_DIVK
+            | LuauOpcode::LOP_IDIVK
+            | LuauOpcode::LOP_MODK
+            | LuauOpcode::LOP_POWK => {
+                self.registers(a, 1)?;
+                self.registers(b, 1)?;
+                self.number_constant(c)?;
+            }
+            LuauOpcode::LOP_ANDK | LuauOpcode::LOP_ORK => {
+                self.registers(a, 1)?;
+                self.registers(b, 1)?;
+                self.constant(c)?;
+            }
+            LuauOpcode::LOP_SUBRK | LuauOpcode::LOP_DIVRK => {
+                self.registers(a, 1)?;
+                self.number_constant(b)?;
+                self.registers(c, 1)?;
+            }
+            LuauOpcode::LOP_CONCAT => {
+                self.registers(a, 1)?;
+                if b > c {
+                    return Err("empty concatenation".to_owned());
+                }
+                self.registers(b, c - b + 1)?;
+            }
+            LuauOpcode::LOP_DUPTABLE => {
+                self.registers(a, 1)?;
+                match self.constant(d as u32)? {
+                    Constant::Table(_) => {}
+                    _ => return Err("table constant expected".to_owned()),
+                }
+            }
+            LuauOpcode::LOP_SETLIST => {
+                self.registers(a, 1)?;
+                self.registers(b, c.saturating_sub(1))?;
+            }
+            LuauOpcode::LOP_FORNPREP
+            | LuauOpcode::LOP_FORNLOOP
+            | LuauOpcode::LOP_FORGPREP
+            | LuauOpcode::LOP_FORGPREP_INEXT
+            | LuauOpcode::LOP_FORGPREP_NEXT => {
+                // limit, step and index, or generator, state and control
+                self.registers(a, 3)?;
+                state.jump(pc, d);
+            }
+            LuauOpcode::LOP_FORGLOOP => {
+                // and the loop variables
+                self.registers(a, 3 + (aux & 0xff))?;
+                state.jump(pc, d);
+            }
+            LuauOpcode::LOP_FASTCALL => self.fastcall(state, pc, a, c)?,
+            LuauOpcode::LOP_FASTCALL1 => {
+                self.registers(b, 1)?;
+                self.fastcall(state, pc, a, c)?;
+            }
+            LuauOpcode::LOP_FASTCALL2 => {
+                self.registers(b, 1)?;
+                self.registers(aux, 1)?;
+                self.fastcall(state, pc, a, c)?;
+            }
+            LuauOpcode::LOP_FASTCALL2K => {
+                self.registers(b, 1)?;
+                self.constant(aux)?;
+                self.fastcall(state, pc, a, c)?;
+            }
+            LuauOpcode::LOP_FASTCALL3 => {
+                self.registers(b, 1)?;
+                self.registers(aux & 0xff, 1)?;
+                self.registers((aux >> 8) & 0xff, 1)?;
+                self.fastcall(state, pc, a, c)?;
+            }
+            LuauOpcode::LOP_PREPVARARGS => {
+                if pc != 0 || !p.is_vararg || a != p.num_params as u32 {
+                    return Err(
+                        "varargs prepared outside the start of a vararg function".to_owned()
+                    );
+                }
+            }
+            _ => return Err("unsupported opcode".to_owned()),
+        }
+
+        // the varargs must be prepared before anything else
+        if pc == 0 && p.is_vararg && insn.op() != LuauOpcode::LOP_PREPVARARGS {
+            return Err("vararg function doesn't start by preparing the varargs".to_owned());
+        }
+
+        Ok(())
+    }
+    /// The closure of function `f` must be followed by a capture of each of its upvalues
+    ///
+    /// Shared closures (`DUPCLOSURE`) can't capture by reference.
+    fn captures(
+        &self,
+        state: &mut CodeState,
+        following: &[(usize, Instruction)],
+        f: usize,
+        shared: bool,
+    ) -> Result<(), String> {
+        let count = self.bytecode.protos[f].num_upvalues as usize;
+        if following.len() < count {
+            return Err("missing captures".to_owned());
+        }
+
+        for &(pc, capture) in &following[..count] {
+            if capture.op() != LuauOpcode::LOP_CAPTURE {
+                return Err("missing captures".to_owned());
+            }
+
+            match capture.a() {
+                CAPTURE_REF if shared => {
+                    return Err("shared closure captures by reference".to_owned());
+                }
+                CAPTURE_VAL | CAPTURE_REF => self.registers(capture.b() as u32, 1)?,
+                CAPTURE_UPVAL => self.upvalue(capture.b() as u32)?,
+                _ => return Err("unknown capture type".to_owned()),
+            }
+            state.captures[pc] = true;
+        }
+
+        Ok(())
+    }
+    /// Fastcalls of the `builtin` are followed by the call that is done if they fail, `skip` instructions later
+    fn fastcall(
+        &self,
+        state: &mut CodeState,
+        pc: usize,
+        builtin: u32,
+        skip: u32,
+    ) -> Result<(), String> {
+        // the VM calls the function from its table without checking
+        if unsafe { shim::luau_hasfastcall(builtin as c_int) } == 0 {
+            return Err(format!("unknown builtin {builtin}"));
+        }
+
+        let call = pc + 1 + skip as usize;
+
+        match state.at.get(call) {
+            Some(Some(insn)) if insn.op() == LuauOpcode::LOP_CALL => {}
+            _ => return Err("fastcall not followed by a call".to_owned()),
+        }
+        // skipped when the fastcall succeeds
+        state.jumps.push((pc, call as i64 + 1));
+
+        Ok(())
+    }
+    /// `count` registers starting at `start`
+    fn registers(&self, start: u32, count: u32) -> Result<(), String> {
+        // the start can be a whole auxiliary word
+        let end = start as u64 + count as u64;
+        if end > self.proto.max_stack_size as u64 {
+            return Err(format!("registers {start}..{end} out of range"));
+        }
+
+        Ok(())
+    }
+    fn upvalue(&self, index: u32) -> Result<(), String> {
+        if index >= self.proto.num_upvalues as u32 {
+            return Err(format!("upvalue {index} out of range"));
+        }
+
+        Ok(())
+    }
+    fn constant(&self, k: u32) -> Result<&Constant, String> {
+        self.proto
+            .constants
+            .get(k as usize)
+            .ok_or_else(|| format!("constant {k} out of range"))
+    }
+    fn number_constant(&self, k: u32) -> Result<(), String> {
+        match self.constant(k)? {
+            Constant::Number(_) => Ok(()),
+            _ => Err(format!("constant {k} is not a number")),
+        }
+    }
+    /// A string constant among the first `limit` constants
+    fn string_constant(&self, k: u32, limit: usize) -> Result<(), String> {
+        match self.proto.constants[..limit].get(k as usize) {
+            Some(Constant::String(_)) => Ok(()),
+            Some(_) => Err(format!("constant {k} is not a string")),
+            None => Err(format!("constant {k} out of range")),
+        }
+    }
+    fn import_path(&self, import: Import, limit: usize) -> Result<(), String> {
+        if import.0 >> 30 == 0 {
+            return Err("empty import".to_owned());
+        }
+
+        import
+            .path()
+            .try_for_each(|k| self.string_constant(k, limit))
+    }
+}
+
+/// Registers that the instruction writes to, as the first one and how many, `u32::MAX` for up to the top
+fn writes(insn: Instruction) -> (u32, u32) {
+    let (a, b, c) = (insn.a() as u32, insn.b() as u32, insn.c() as u32);
+
+    // the count is stored plus one, 0 means up to the top of the stack
+    let count = |n: u32| n.checked_sub(1).unwrap_or(u32::MAX);
+
+    match insn.op() {
+        LuauOpcode::LOP_LOADNIL
+        | LuauOpcode::LOP_LOADN
+        | LuauOpcode::LOP_LOADK
+        | LuauOpcode::LOP_LOADKX
+        | LuauOpcode::LOP_MOVE
+        | LuauOpcode::LOP_GETGLOBAL
+        | LuauOpcode::LOP_GETUPVAL
+        | LuauOpcode::LOP_GETIMPORT
+        | LuauOpcode::LOP_GETTABLE
+        | LuauOpcode::LOP_GETTABLEKS
+        | LuauOpcode::LOP_GETTABLEN
+        | LuauOpcode::LOP_NEWCLOSURE
+        | LuauOpcode::LOP_DUPCLOSURE
+        | LuauOpcode::LOP_ADD
+        | LuauOpcode::LOP_SUB
+        | LuauOpcode::LOP_MUL
+        | LuauOpcode::LOP_DIV
+        | LuauOpcode::LOP_IDIV
+        | LuauOpcode::LOP_MOD
+        | LuauOpcode::LOP_POW
+        | LuauOpcode::LOP_ADDK
+        | LuauOpcode::LOP_SUBK
+        | LuauOpcode::LOP_MULK
+        | LuauOpcode::LOP_DIVK
+        | LuauOpcode::LOP_IDIVK
+        | LuauOpcode::LOP_MODK
+        | LuauOpcode::LOP_POWK
+        | LuauOpcode::LOP_AND
+        | LuauOpcode::LOP_OR
+        | LuauOpcode::LOP_ANDK
+        | LuauOpcode::LOP_ORK
+        | LuauOpcode::LOP_SUBRK
+        | LuauOpcode::LOP_DIVRK
+        | LuauOpcode::LOP_CONCAT
+        | LuauOpcode::LOP_NOT
+        | LuauOpcode::LOP_MINUS
+        | LuauOpcode::LOP_LENGTH
+        | LuauOpcode::LOP_NEWTABLE
+        | LuauOpcode::LOP_DUPTABLE
+        | LuauOpcode::LOP_LOADB => (a, 1),
+        LuauOpcode::LOP_NAMECALL => (a, 2),
+        LuauOpcode::LOP_CALL => (a, count(c)),
+        LuauOpcode::LOP_GETVARARGS => (a, count(b)),
+        LuauOpcode::LOP_FORNPREP | LuauOpcode::LOP_FORNLOOP => (a, 3),
+        // the fixed arguments are moved
+        LuauOpcode::LOP_PREPVARARGS => (0, u32::MAX),
+        _ => (0, 0),
+    }
+}
+
+/// Generic `for` loops with their iteration state intact, and registers captured by reference
+#[derive(Debug, Clone, Default, PartialEq)]
+struct LoopState {
+    /// the first register of each loop
+    loops: Registers,
+    captured: Registers,
+}
+
+impl LoopState {
+    /// Registers `start..start + count` were overwritten, so are the loops using them
+    fn write(&mut self, start: u32, count: u32) {
+        let end = start as u64 + count as u64;
+
+        for base in 0..=255 {
+            if self.loops.contains(base) && (base as u64) < end && start < base + 3 {
+                self.loops.remove(base);
+            }
+        }
+    }
+}
+
+/// Set of registers
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Registers([u64; 4]);
+
+impl Registers {
+    fn contains(&self, r: u32) -> bool {
+        r < 256 && self.0[r as usize / 64] & (1 << (r % 64)) != 0
+    }
+    fn insert(&mut self, r: u32) {
+        if r < 256 {
+            self.0[r as usize / 64] |= 1 << (r % 64);
+        }
+    }
+    fn remove(&mut self, r: u32) {
+        if r < 256 {
+            self.0[r as usize / 64] &= !(1 << (r % 64));
+        }
+    }
+    /// Removes `r` and all registers after it
+    fn remove_from(&mut self, r: u32) {
+        for r in r..256 {
+            self.remove(r);
+        }
+    }
+    fn union(&self, other: &Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] | other.0[i]))
+    }
+    fn intersection(&self, other: &Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] & other.0[i]))
+    }
+}
+
+/// What is known about the code of a function while checking its instructions
+struct CodeState {
+    /// instructions by their position, `None` for auxiliary words
+    at: Vec<Option<Instruction>>,
+    /// positions of captures that belong to a closure
+    captures: Vec<bool>,
+    /// jumps to check once all instructions are known, with the position of the instruction
+    jumps: Vec<(usize, i64)>,
+}
+
+impl CodeState {
+    /// Jumps are relative to the instruction after the jump, not counting the auxiliary word
+    fn jump(&mut self, pc: usize, offset: i32) {
+        self.jumps.push((pc, pc as i64 + 1 + offset as i64));
+    }
+}
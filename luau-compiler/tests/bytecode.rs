@@ -0,0 +1,120 @@
+use luau_compiler::{
+    bytecode::{Constant, Import},
+    compile, Bytecode, BytecodeError, CompilerOptions, DebugLevel,
+};
+
+#[test]
+fn test_parse_bytecode() {
+    let mut opts = CompilerOptions::new();
+    opts.debug = DebugLevel::Full;
+
+    let source = r#"local function add(a, b)
+    return a + b
+end
+
+print(add(1, 2), math.max(3, 4))"#;
+    let bytecode = Bytecode::parse(&compile(source, &opts).unwrap()).unwrap();
+
+    assert_eq!(bytecode.protos.len(), 2);
+    let main = &bytecode.protos[bytecode.main];
+    assert!(main.is_vararg);
+
+    let add = &bytecode.protos[main.children[0]];
+    assert_eq!(add.num_params, 2);
+    assert_eq!(add.line_defined, 1);
+    assert_eq!(bytecode.string(add.debug_name.unwrap()), Some(&b"add"[..]));
+    assert_eq!(add.line(0), Some(2));
+
+    let locals = &add.debug_info.as_ref().unwrap().locals;
+    let names: Vec<_> = locals
+        .iter()
+        .map(|l| bytecode.string(l.name.unwrap()).unwrap())
+        .collect();
+    assert_eq!(names, [b"a", b"b"]);
+
+    // imports are paths of string constants
+    let imports: Vec<Vec<&[u8]>> = main
+        .constants
+        .iter()
+        .filter_map(|k| match k {
+            Constant::Import(import) => Some(import_path(&bytecode, &main.constants, *import)),
+            _ => None,
+        })
+        .collect();
+    assert!(imports.contains(&vec![&b"print"[..]]));
+    assert!(imports.contains(&vec![&b"math"[..], &b"max"[..]]));
+
+    // auxiliary words are part of the instructions
+    assert!(main.instructions().count() < main.code.len());
+}
+
+fn import_path<'b>(
+    bytecode: &'b Bytecode,
+    constants: &[Constant],
+    import: Import,
+) -> Vec<&'b [u8]> {
+    import
+        .path()
+        .map(|k| match constants[k as usize] {
+            Constant::String(s) => bytecode.string(s).unwrap(),
+            _ => panic!("import of a non string constant"),
+        })
+        .collect()
+}
+
+#[test]
+fn test_disassemble() {
+    let bytecode = compile(
+        "local t = {x = 1}\nreturn function() return t.x + 2.5 end",
+        &CompilerOptions::new(),
+    )
+    .unwrap();
+    let listing = Bytecode::parse(&bytecode).unwrap().disassemble();
+
+    assert!(listing.contains("(main)"), "{listing}");
+    assert!(listing.contains("DUPTABLE"), "{listing}");
+    assert!(listing.contains("table {x}"), "{listing}");
+    assert!(listing.contains("GETTABLEKS"), "{listing}");
+    assert!(listing.contains("= \"x\""), "{listing}");
+    assert!(listing.contains("ADDK"), "{listing}");
+    assert!(listing.contains("= 2.5"), "{listing}");
+    assert!(listing.contains("CLOSURE"), "{listing}");
+}
+
+#[test]
+fn test_disassemble_mutable_globals() {
+    let mut opts = CompilerOptions::new();
+    let source = "print(a)";
+
+    let imported = Bytecode::parse(&compile(source, &opts).unwrap())
+        .unwrap()
+        .disassemble();
+    assert!(imported.contains("import a"), "{imported}");
+    assert!(!imported.contains("GETGLOBAL"), "{imported}");
+
+    opts.set_mutable_globals(["a"]);
+    let mutable = Bytecode::parse(&compile(source, &opts).unwrap())
+        .unwrap()
+        .disassemble();
+    assert!(!mutable.contains("import a"), "{mutable}");
+    assert!(mutable.contains("GETGLOBAL"), "{mutable}");
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(Bytecode::parse(&[]), Err(BytecodeError::Truncated));
+    assert!(matches!(
+        Bytecode::parse(&[255]),
+        Err(BytecodeError::Version { version: 255, .. })
+    ));
+    assert!(matches!(
+        Bytecode::parse(b"\0:1: oops"),
+        Err(BytecodeError::Compile(_))
+    ));
+
+    let bytecode = compile("return 1", &CompilerOptions::new()).unwrap();
+    assert_eq!(
+        Bytecode::parse(&bytecode[..bytecode.len() - 1]),
+        Err(BytecodeError::Truncated)
+    );
+}
@@ -0,0 +1,101 @@
+use luau_compiler::{
+    cache::{CacheBackend, CacheKey, DiskCache, MemoryCache},
+    compile, CompileCache, CompilerOptions, Constant, LibraryWithKnownMembers, OptLevel,
+};
+use std::{env, fs};
+
+#[test]
+fn test_cache_memory() {
+    let mut cache = CompileCache::new(MemoryCache::new(2));
+    let opts = CompilerOptions::new();
+
+    let first = cache.compile("return 1", &opts).unwrap();
+    let second = cache.compile("return 1", &opts).unwrap();
+    assert_eq!(*first, *compile("return 1", &opts).unwrap());
+    assert_eq!(first, second);
+    assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+    // different options are a different entry
+    let mut max = CompilerOptions::new();
+    max.optimization = OptLevel::Max;
+    cache.compile("return 1", &max).unwrap();
+    assert_eq!((cache.hits(), cache.misses()), (1, 2));
+
+    // "return 1" with the default options was used least recently
+    cache.compile("return 2", &opts).unwrap();
+    assert_eq!(cache.backend().len(), 2);
+    cache.compile("return 1", &max).unwrap();
+    cache.compile("return 1", &opts).unwrap();
+    assert_eq!((cache.hits(), cache.misses()), (2, 4));
+
+    // errors are not cached
+    assert!(cache.compile("return (", &opts).is_err());
+    assert!(cache.compile("return (", &opts).is_err());
+    assert_eq!((cache.hits(), cache.misses()), (2, 6));
+}
+
+#[test]
+fn test_cache_key() {
+    let mut a = CompilerOptions::new();
+    let mut b = CompilerOptions::new();
+    assert_eq!(CacheKey::new("x", &a), CacheKey::new("x", &b));
+    assert_ne!(CacheKey::new("x", &a), CacheKey::new("y", &a));
+
+    // the order of map entries doesn't matter
+    let mut one = LibraryWithKnownMembers::new("lib");
+    let mut two = LibraryWithKnownMembers::new("lib");
+    for i in 0..16 {
+        one.constants
+            .insert(format!("k{i}"), Constant::Number(i as f64));
+    }
+    for i in (0..16).rev() {
+        two.constants
+            .insert(format!("k{i}"), Constant::Number(i as f64));
+    }
+    a.add_known_library(one);
+    b.add_known_library(two);
+    assert_eq!(CacheKey::new("x", &a), CacheKey::new("x", &b));
+
+    b.set_mutable_globals(["x"]);
+    assert_ne!(CacheKey::new("x", &a), CacheKey::new("x", &b));
+}
+
+#[test]
+fn test_cache_disk() {
+    let dir = env::temp_dir().join(format!("luau-compile-cache-{}", std::process::id()));
+    let opts = CompilerOptions::new();
+
+    let mut cache = CompileCache::new(DiskCache::new(&dir).unwrap());
+    let bytecode = cache.compile("return 1", &opts).unwrap();
+    assert_eq!(cache.misses(), 1);
+
+    // a new cache over the same directory, like in the next run
+    let mut cache = CompileCache::new(DiskCache::new(&dir).unwrap());
+    assert_eq!(cache.compile("return 1", &opts).unwrap(), bytecode);
+    assert_eq!(cache.hits(), 1);
+
+    // same file name but different source code, like when the short hashes collide
+    let mut collided = CacheKey::new("return 1", &opts);
+    collided.digest = CacheKey::new("return 3", &opts).digest;
+    assert_eq!(cache.backend_mut().get(&collided), None);
+
+    // entries of another Luau version are never read, and pruned
+    let mut key = CacheKey::new("return 2", &opts);
+    key.version ^= 1;
+    cache.backend_mut().insert(key, bytecode.clone());
+    assert_eq!(cache.backend_mut().get(&key), Some(bytecode));
+
+    // along with temporary files left behind by a process that died while writing, of any version
+    let partial = CacheKey::new("return 3", &opts);
+    let temp = format!(
+        "{:016x}-{:016x}-{:016x}.1.tmp.luauc",
+        partial.source, partial.options, partial.version
+    );
+    fs::write(dir.join(temp), b"partial").unwrap();
+    assert_eq!(cache.backend().prune().unwrap(), 2);
+    assert_eq!(cache.backend_mut().get(&key), None);
+
+    cache.backend().clear().unwrap();
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+    fs::remove_dir(&dir).unwrap();
+}
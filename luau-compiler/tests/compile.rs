@@ -1,4 +1,4 @@
-use luau_compiler::{compile, CompilerOptions};
+use luau_compiler::{compile, Bytecode, CompilerOptions};
 
 #[test]
 fn test_compile_simple() {
@@ -18,7 +18,8 @@ fn test_compile_simple() {
 
 #[test]
 fn test_compile_mut_globals_opt() {
-    // Tests compiling with different mutable globals list and checking if the bytecode is different
+    // Tests compiling with and without a mutable globals list, the bytecode is the same because
+    // the chunk assigns the global itself
     let mut opts = CompilerOptions::new();
 
     let code = r#"function test()
@@ -35,9 +36,33 @@ fn test_compile_mut_globals_opt() {
 
     assert!(without.is_ok(), "this must compile correctly");
     assert!(with.is_ok(), "this must compile correctly");
-    // assert_ne!(
-    //     without.unwrap(),
-    //     with.unwrap(),
-    //     "bytecode must not be identical"
-    // ); // TODO fails for some reason
+    // the compiler already treats assigned globals as mutable, see `test_disassemble_mutable_globals`
+    // for a global that is only read
+    assert_eq!(
+        Bytecode::parse(&without.unwrap()).unwrap(),
+        Bytecode::parse(&with.unwrap()).unwrap(),
+        "bytecode must be identical"
+    );
+}
+
+#[test]
+fn test_compile_error_location() {
+    let opts = CompilerOptions::new();
+
+    let err = compile("local a = 1\n    local b = = 2\nreturn a", &opts).unwrap_err();
+    assert_eq!(err.line(), Some(2));
+    assert_eq!(
+        err.message(),
+        "Expected identifier when parsing expression, got '='"
+    );
+    assert_eq!(err.line_text(), Some("    local b = = 2"));
+
+    assert_eq!(
+        format!("{err:#}"),
+        "luau compile error: Expected identifier when parsing expression, got '='
+ --> line 2
+  |
+2 |     local b = = 2
+  |     ^^^^^^^^^^^^^"
+    );
 }
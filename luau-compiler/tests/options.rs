@@ -0,0 +1,108 @@
+use luau_compiler::{
+    CompilerOptions, Constant, CoverageLevel, DebugLevel, LibraryWithKnownMembers, OptLevel,
+};
+use luau_sys::common::bytecode::LuauBytecodeType;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+fn hash(value: &impl Hash) -> u64 {
+    // the default hasher of `DefaultHasher::new` always uses the same keys
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn options(reverse: bool) -> CompilerOptions {
+    let mut library = LibraryWithKnownMembers::new("game");
+    let mut names: Vec<_> = (0..16).map(|i| format!("member{i}")).collect();
+    if reverse {
+        names.reverse();
+    }
+    for name in names {
+        library
+            .types
+            .insert(name.clone(), LuauBytecodeType::LBC_TYPE_NUMBER);
+        library.constants.insert(name, Constant::Number(1.5));
+    }
+
+    let mut options = CompilerOptions::new();
+    options.optimization = OptLevel::Max;
+    options.debug = DebugLevel::Full;
+    options.coverage = CoverageLevel::Statement;
+    options
+        .set_alt_vector("Vector3", "new", "Vector3")
+        .set_mutable_globals(["state"])
+        .set_userdata_types(["Part"])
+        .set_disabled_builtins(["math.floor"])
+        .add_known_library(library);
+    options
+}
+
+#[test]
+fn test_options_hash() {
+    assert_eq!(options(false), options(true));
+    assert_eq!(hash(&options(false)), hash(&options(true)));
+    assert_ne!(hash(&options(false)), hash(&CompilerOptions::new()));
+
+    let mut other = options(false);
+    other.set_mutable_globals(["state", "other"]);
+    assert_ne!(options(false), other);
+    assert_ne!(hash(&options(false)), hash(&other));
+
+    // numbers are compared by their bits
+    assert_eq!(Constant::Number(f64::NAN), Constant::Number(f64::NAN));
+    assert_ne!(Constant::Number(0.0), Constant::Number(-0.0));
+    assert_ne!(hash(&Constant::Number(0.0)), hash(&Constant::Number(-0.0)));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_options_serde() {
+    let options = options(false);
+
+    let json = serde_json::to_string(&options).unwrap();
+    // maps are written sorted
+    assert_eq!(json, serde_json::to_string(&self::options(true)).unwrap());
+    assert!(json.contains(r#""optimization":"max""#));
+    assert!(json.contains(r#""member0":"number""#));
+    assert_eq!(
+        serde_json::from_str::<CompilerOptions>(&json).unwrap(),
+        options
+    );
+
+    // missing fields are the defaults
+    let partial: CompilerOptions = serde_json::from_str(
+        r#"{
+            "debug": "full",
+            "known_libraries": [{
+                "name": "lib",
+                "types": {"a": "string?", "b": 64},
+                "constants": {"c": {"vector": [1, 2, 3, 0]}, "d": "nil"}
+            }]
+        }"#,
+    )
+    .unwrap();
+    let mut library = LibraryWithKnownMembers::new("lib");
+    library.types.insert(
+        "a".to_owned(),
+        LuauBytecodeType(
+            LuauBytecodeType::LBC_TYPE_STRING.0 | LuauBytecodeType::LBC_TYPE_OPTIONAL_BIT.0,
+        ),
+    );
+    library.types.insert(
+        "b".to_owned(),
+        LuauBytecodeType::LBC_TYPE_TAGGED_USERDATA_BASE,
+    );
+    library
+        .constants
+        .insert("c".to_owned(), Constant::Vector(1.0, 2.0, 3.0, 0.0));
+    library.constants.insert("d".to_owned(), Constant::Nil);
+    let mut expected = CompilerOptions::new();
+    expected.debug = DebugLevel::Full;
+    expected.add_known_library(library);
+    assert_eq!(partial, expected);
+
+    assert!(serde_json::from_str::<CompilerOptions>(r#"{"optimisation": "max"}"#).is_err());
+    assert!(
+        serde_json::from_str::<CompilerOptions>(r#"{"mutable_globals": ["a\u0000"]}"#).is_err()
+    );
+}
@@ -0,0 +1,262 @@
+use luau_compiler::bytecode::{Constant, Proto};
+use luau_compiler::{compile, Bytecode, BytecodeError, CompilerOptions, DebugLevel};
+use luau_sys::common::bytecode::LuauOpcode;
+
+fn parse(source: &str) -> Bytecode {
+    let mut opts = CompilerOptions::new();
+    opts.debug = DebugLevel::Full;
+
+    Bytecode::parse(&compile(source, &opts).unwrap()).unwrap()
+}
+
+const SOURCE: &str = r#"local count = 0
+local function inc(n)
+    count += n
+    return count
+end
+
+for i = 1, 10 do
+    if i % 2 == 0 then
+        inc(i)
+    end
+end
+
+local fns = {}
+for k, v in pairs({a = 1, b = 2}) do
+    local seen = 0
+    fns[k] = function() seen += v return seen end
+    for _, x in ipairs({v, v}) do
+        if x > 1 then break end
+        inc(x)
+    end
+end
+
+print(("total: %d"):format(inc(0)), math.max(1, 2), {a = 1, b = {}})"#;
+
+#[test]
+fn test_verify_compiled() {
+    parse(SOURCE).verify().unwrap();
+    parse("return ...").verify().unwrap();
+    parse("").verify().unwrap();
+}
+
+#[test]
+fn test_verify_errors() {
+    let bytecode = parse(SOURCE);
+    let main = bytecode.main;
+
+    let fails = |modify: &dyn Fn(&mut Bytecode)| {
+        let mut bytecode = bytecode.clone();
+        modify(&mut bytecode);
+        match bytecode.verify() {
+            Err(BytecodeError::Invalid(_)) => {}
+            other => panic!("expected verification to fail, got {other:?}"),
+        }
+    };
+
+    // registers out of the stack
+    fails(&|b| b.protos[main].max_stack_size = 0);
+    // constants that are referred to
+    fails(&|b| b.protos[main].constants.clear());
+    // closures that are created
+    fails(&|b| b.protos[main].children.clear());
+    // the main function can't have upvalues
+    fails(&|b| b.protos[main].num_upvalues = 1);
+    // falling off the end of the code
+    fails(&|b| {
+        b.protos[main].code.pop();
+    });
+    // jumping out of the code
+    fails(&|b| {
+        let jump = LuauOpcode::LOP_JUMP.0 as u32 | (1000 << 16);
+        b.protos[main].code.push(jump);
+    });
+    // unknown opcodes
+    fails(&|b| b.protos[main].code[0] = 0xff);
+}
+
+fn abc(op: LuauOpcode, a: u8, b: u8, c: u8) -> u32 {
+    op.0 as u32 | (a as u32) << 8 | (b as u32) << 16 | (c as u32) << 24
+}
+
+fn ad(op: LuauOpcode, a: u8, d: i16) -> u32 {
+    op.0 as u32 | (a as u32) << 8 | ((d as u16) as u32) << 16
+}
+
+/// The code as the main function, with 8 registers
+fn with_code(code: Vec<u32>) -> Bytecode {
+    let mut bytecode = parse("");
+    let main = &mut bytecode.protos[bytecode.main];
+    main.is_vararg = false;
+    main.max_stack_size = 8;
+    main.type_info.clear();
+    main.line_info = None;
+    main.debug_info = None;
+    main.code = code;
+
+    bytecode
+}
+
+/// Adds a function that returns its only upvalue
+fn add_child(bytecode: &mut Bytecode) -> usize {
+    bytecode.protos.push(Proto {
+        max_stack_size: 1,
+        num_params: 0,
+        num_upvalues: 1,
+        is_vararg: false,
+        flags: 0,
+        type_info: Vec::new(),
+        code: vec![
+            abc(LuauOpcode::LOP_GETUPVAL, 0, 0, 0),
+            abc(LuauOpcode::LOP_RETURN, 0, 2, 0),
+        ],
+        constants: Vec::new(),
+        children: Vec::new(),
+        line_defined: 0,
+        debug_name: None,
+        line_info: None,
+        debug_info: None,
+    });
+
+    bytecode.protos.len() - 1
+}
+
+fn verifies(bytecode: &Bytecode) -> bool {
+    match bytecode.verify() {
+        Ok(()) => true,
+        Err(BytecodeError::Invalid(_)) => false,
+        Err(e) => panic!("unexpected error {e:?}"),
+    }
+}
+
+#[test]
+fn test_verify_register_overflow() {
+    let jump = |aux| {
+        with_code(vec![
+            // skips the auxiliary word
+            ad(LuauOpcode::LOP_JUMPIFEQ, 0, 1),
+            aux,
+            abc(LuauOpcode::LOP_RETURN, 0, 1, 0),
+        ])
+    };
+
+    assert!(verifies(&jump(1)));
+    // doesn't wrap around to a small register
+    assert!(!verifies(&jump(u32::MAX)));
+    assert!(!verifies(&jump(8)));
+}
+
+#[test]
+fn test_verify_fastcall_builtin() {
+    let fastcall = |builtin| {
+        with_code(vec![
+            abc(LuauOpcode::LOP_FASTCALL1, builtin, 1, 0),
+            abc(LuauOpcode::LOP_CALL, 0, 2, 2),
+            abc(LuauOpcode::LOP_RETURN, 0, 2, 0),
+        ])
+    };
+
+    // LBF_ASSERT
+    assert!(verifies(&fastcall(1)));
+    assert!(!verifies(&fastcall(0)));
+    assert!(!verifies(&fastcall(255)));
+}
+
+#[test]
+fn test_verify_shared_closure_captures() {
+    let dupclosure = |capture_type| {
+        let mut bytecode = with_code(vec![
+            ad(LuauOpcode::LOP_DUPCLOSURE, 0, 0),
+            abc(LuauOpcode::LOP_CAPTURE, capture_type, 1, 0),
+            abc(LuauOpcode::LOP_RETURN, 0, 2, 0),
+        ]);
+
+        let child = add_child(&mut bytecode);
+        let main = bytecode.main;
+        bytecode.protos[main].constants = vec![Constant::Closure(child)];
+
+        bytecode
+    };
+
+    // LCT_VAL, LCT_REF and LCT_UPVAL
+    assert!(verifies(&dupclosure(0)));
+    assert!(!verifies(&dupclosure(1)));
+    // the main function has no upvalues to capture
+    assert!(!verifies(&dupclosure(2)));
+}
+
+#[test]
+fn test_verify_generic_loops() {
+    let nil = abc(LuauOpcode::LOP_LOADNIL, 0, 0, 0);
+    let table = abc(LuauOpcode::LOP_NEWTABLE, 1, 0, 0);
+    let forgloop = ad(LuauOpcode::LOP_FORGLOOP, 0, -1);
+    let ret = abc(LuauOpcode::LOP_RETURN, 0, 1, 0);
+
+    // nil, table and the position set up by FORGPREP_NEXT, the loop jumps back to itself
+    assert!(verifies(&with_code(vec![
+        nil,
+        table,
+        0,
+        ad(LuauOpcode::LOP_FORGPREP_NEXT, 0, 0),
+        forgloop,
+        2,
+        ret,
+    ])));
+
+    // the position set up by hand
+    assert!(!verifies(&with_code(vec![
+        nil,
+        table,
+        0,
+        ad(LuauOpcode::LOP_LOADN, 2, 5),
+        forgloop,
+        2,
+        ret,
+    ])));
+
+    // the position overwritten in the loop
+    assert!(!verifies(&with_code(vec![
+        nil,
+        table,
+        0,
+        ad(LuauOpcode::LOP_FORGPREP_NEXT, 0, 1),
+        ad(LuauOpcode::LOP_LOADN, 2, 5),
+        ad(LuauOpcode::LOP_FORGLOOP, 0, -2),
+        2,
+        ret,
+    ])));
+
+    // the loop entered on one path without FORGPREP
+    assert!(!verifies(&with_code(vec![
+        nil,
+        table,
+        0,
+        ad(LuauOpcode::LOP_JUMPIF, 1, 1),
+        ad(LuauOpcode::LOP_FORGPREP_NEXT, 0, 0),
+        forgloop,
+        2,
+        ret,
+    ])));
+
+    // the table captured by reference, the closure could replace it
+    let captured = |capture_type| {
+        let mut bytecode = with_code(vec![
+            nil,
+            table,
+            0,
+            ad(LuauOpcode::LOP_NEWCLOSURE, 3, 0),
+            abc(LuauOpcode::LOP_CAPTURE, capture_type, 1, 0),
+            ad(LuauOpcode::LOP_FORGPREP_NEXT, 0, 0),
+            forgloop,
+            2,
+            ret,
+        ]);
+        let child = add_child(&mut bytecode);
+        let main = bytecode.main;
+        bytecode.protos[main].children = vec![child];
+
+        bytecode
+    };
+    assert!(verifies(&captured(0)));
+    assert!(!verifies(&captured(1)));
+}
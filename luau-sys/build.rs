@@ -1,5 +1,65 @@
-use bindgen::Builder;
-use std::{env, fs, path::PathBuf, process::Command};
+use bindgen::{
+    callbacks::{ItemInfo, ParseCallbacks},
+    Builder,
+};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Strips the `shim_` prefix from the shim functions, so they have the same names as
+/// the original functions, just in a different module. Same for the `SHIM_` constants
+#[derive(Debug)]
+struct ShimNames;
+
+impl ParseCallbacks for ShimNames {
+    fn generated_name_override(&self, item_info: ItemInfo<'_>) -> Option<String> {
+        item_info
+            .name
+            .strip_prefix("shim_")
+            .or_else(|| item_info.name.strip_prefix("SHIM_"))
+            .map(str::to_owned)
+    }
+}
+
+/// Hash of the sources of the luau parser and compiler, which change with every change to the bytecode they
+/// produce. Unlike the git description it's available without the git history, like in a packaged crate.
+fn compiler_hash(luau_source: &Path) -> u64 {
+    fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+        for entry in fs::read_dir(dir).expect("reading the luau sources") {
+            let path = entry.expect("reading the luau sources").path();
+            if path.is_dir() {
+                collect(&path, files);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    for dir in ["Ast", "Compiler", "Common"] {
+        collect(&luau_source.join(dir), &mut files);
+    }
+    files.sort();
+
+    // 64 bit FNV-1a, over the path of each file relative to the luau sources and its contents
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut write = |bytes: &[u8]| {
+        for &b in bytes {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    };
+    for file in files {
+        let relative = file.strip_prefix(luau_source).unwrap();
+        write(relative.to_string_lossy().replace('\\', "/").as_bytes());
+        write(&[0]);
+        write(&fs::read(&file).expect("reading the luau sources"));
+    }
+
+    hash
+}
 
 fn main() {
     // fetch the git submodules
@@ -13,6 +73,14 @@ fn main() {
         .status()
         .expect("Failed to fetch git submodules");
 
+    // luau vectors have 3 components by default, 4 can be chosen when building
+    let vector_size = match env::var("LUAU_VECTOR_SIZE").as_deref() {
+        Ok("3") | Err(_) => 3,
+        Ok("4") => 4,
+        Ok(other) => panic!("LUAU_VECTOR_SIZE must be 3 or 4, got {other:?}"),
+    };
+    let vector_define = format!("-DLUA_VECTOR_SIZE={vector_size}");
+
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
 
     let mut build_dir = out_dir.clone();
@@ -20,16 +88,30 @@ fn main() {
     fs::create_dir_all(&build_dir).unwrap();
 
     let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-    let mut luau_source = PathBuf::from(manifest_dir);
+    let mut luau_source = PathBuf::from(&manifest_dir);
     luau_source.push("..");
     luau_source.push("vendor");
     luau_source.push("luau");
 
+    // identifies the vendored luau, for example to tell apart bytecode compiled by different versions
+    let luau_version = Command::new("git")
+        .arg("-C")
+        .arg(&luau_source)
+        .args(["describe", "--tags", "--always", "--dirty"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_owned())
+        .unwrap_or_else(|| "unknown".to_owned());
+    let compiler_hash = compiler_hash(&luau_source);
+
     // generate the build scripts
     Command::new("cmake")
         .arg("-DLUAU_BUILD_CLI=OFF")
         .arg("-DLUAU_BUILD_TESTS=OFF")
         .arg("-DCMAKE_BUILD_TYPE=RelWithDebInfo")
+        .arg(format!("-DCMAKE_CXX_FLAGS={vector_define}"))
         .arg("-S")
         .arg(&luau_source)
         .arg("-B")
@@ -50,6 +132,23 @@ fn main() {
         .status()
         .expect("failed to build luau");
 
+    let mut shim_source = PathBuf::from(&manifest_dir);
+    shim_source.push("..");
+    shim_source.push("shim");
+
+    // build the exception catching shim
+    // must be done before linking luau itself, since the shim depends on it
+    cc::Build::new()
+        .cpp(true)
+        .file(shim_source.join("lua.cpp"))
+        .include(&shim_source)
+        .include(luau_source.join("VM").join("include"))
+        .include(luau_source.join("VM").join("src")) // the shim uses internal VM headers
+        .include(luau_source.join("Common").join("include"))
+        .flag_if_supported("-std=c++17")
+        .define("LUA_VECTOR_SIZE", vector_size.to_string().as_str())
+        .compile("luau_shim");
+
     // build the rust bindings
     let vm_bindings = Builder::default()
         .header(
@@ -62,6 +161,7 @@ fn main() {
         )
         .allowlist_item("[Ll]ua.*") // only generate for stuff starting with lua
         .newtype_enum(".*") // generate all enums in newtype enum flavor
+        .clang_arg(&vector_define) // changes the signatures of the vector functions
         .clang_arg("-fparse-all-comments") // keeps the comments
         .clang_args(["-x", "c++"]) // c++ mode even though the file is .h
         .generate()
@@ -98,6 +198,23 @@ fn main() {
         .generate()
         .expect("generating Common/Bytecode bindings");
 
+    let shim_bindings = Builder::default()
+        .header(shim_source.join("shim.h").to_str().unwrap())
+        .clang_arg(format!(
+            "-I{}",
+            luau_source.join("VM").join("include").display()
+        ))
+        .clang_arg(&vector_define)
+        .allowlist_function("shim_.*") // only the shim functions, types are reused from the vm bindings
+        .allowlist_var("SHIM_.*")
+        .allowlist_recursively(false)
+        .raw_line("use crate::vm::*;")
+        .parse_callbacks(Box::new(ShimNames))
+        .clang_arg("-fparse-all-comments") // keeps the comments
+        .clang_args(["-x", "c++"]) // c++ mode even though the file is .h
+        .generate()
+        .expect("generating shim bindings");
+
     // write the bindings to the OUT_DIR
     // these are included! from in lib.rs
     vm_bindings
@@ -109,8 +226,19 @@ fn main() {
     commmon_bytecode_bindings
         .write_to_file(out_dir.join("common_bytecode_bindings.rs"))
         .unwrap();
+    shim_bindings
+        .write_to_file(out_dir.join("shim_bindings.rs"))
+        .unwrap();
 
     println!("cargo:rerun-if-changed=../vendor/");
+    println!("cargo:rerun-if-changed=../shim/");
+    println!("cargo:rerun-if-env-changed=LUAU_VECTOR_SIZE");
+    println!("cargo:rustc-env=LUAU_VERSION={luau_version}");
+    println!("cargo:rustc-env=LUAU_COMPILER_HASH={compiler_hash:016x}");
+    println!("cargo::rustc-check-cfg=cfg(luau_vector4)");
+    if vector_size == 4 {
+        println!("cargo:rustc-cfg=luau_vector4");
+    }
     println!("cargo:rustc-link-search=native={}", build_dir.display());
     println!("cargo:rustc-link-lib=static=Luau.VM");
     println!("cargo:rustc-link-lib=static=Luau.Compiler");
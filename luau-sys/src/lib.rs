@@ -15,3 +15,56 @@ pub mod common {
         include!(concat!(env!("OUT_DIR"), "/common_bytecode_bindings.rs"));
     }
 }
+
+/// Exception-safe wrappers around the fallible VM functions.
+///
+/// The functions have the same names and arguments as their counterparts in [`vm`], but they catch
+/// any luau errors and return the status instead. On error, the arguments consumed by the function
+/// are replaced with the error object on top of the stack. Return values are written to the `out` pointer.
+pub mod shim {
+    include!(concat!(env!("OUT_DIR"), "/shim_bindings.rs"));
+}
+
+/// Version of the vendored luau as described by git, the release tag or the commit hash, or `"unknown"`
+/// without the git history
+pub const LUAU_VERSION: &str = env!("LUAU_VERSION");
+
+/// Hash of the sources of the vendored luau parser and compiler, changes with any change to the bytecode
+/// they produce even when the git description is unknown
+pub const LUAU_COMPILER_HASH: &str = env!("LUAU_COMPILER_HASH");
+
+/// Number of components of luau vectors, 3 unless built with the `LUAU_VECTOR_SIZE=4` environment variable
+pub const VECTOR_SIZE: usize = if cfg!(luau_vector4) { 4 } else { 3 };
+
+/// [`vm::lua_pushvector`] and [`vm::lua_tovector`] with the same signatures for both vector sizes
+pub mod vector {
+    use crate::{
+        vm::{lua_State, lua_pushvector, lua_tovector},
+        VECTOR_SIZE,
+    };
+    use std::ffi::c_int;
+
+    /// Pushes a vector with the components
+    pub unsafe fn push_vector(l: *mut lua_State, v: [f32; VECTOR_SIZE]) {
+        #[cfg(not(luau_vector4))]
+        unsafe {
+            let [x, y, z] = v;
+            lua_pushvector(l, x, y, z)
+        }
+        #[cfg(luau_vector4)]
+        unsafe {
+            let [x, y, z, w] = v;
+            lua_pushvector(l, x, y, z, w)
+        }
+    }
+
+    /// Returns the components of the vector at `idx`, or `None` if the value is not a vector
+    pub unsafe fn to_vector(l: *mut lua_State, idx: c_int) -> Option<[f32; VECTOR_SIZE]> {
+        let v = unsafe { lua_tovector(l, idx) };
+        if v.is_null() {
+            return None;
+        }
+
+        Some(unsafe { *(v as *const [f32; VECTOR_SIZE]) })
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{
+    convert::{expect_type, FromLuau, FromLuauArg, IntoLuau},
+    error::{check, Error, Result},
+    stack::StackRef,
+    state::LuauState,
+};
+use luau_sys::{
+    shim,
+    vm::{lua_Type, lua_rawequal, lua_tobuffer, lua_topointer},
+};
+use std::{
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+    ptr::null_mut,
+    slice,
+};
+
+/// Handle to a luau buffer, a fixed size block of mutable bytes
+///
+/// The bytes are accessed through [`Buffer::borrow`] and [`Buffer::borrow_mut`]. While any buffer is borrowed,
+/// running luau code fails with [`Error::BorrowedBuffer`], since it could modify the bytes.
+pub struct Buffer<'a> {
+    pub(crate) value: StackRef<'a>,
+}
+
+impl LuauState {
+    /// Creates a new buffer with a copy of the bytes
+    pub fn create_buffer(&self, bytes: &[u8]) -> Result<Buffer> {
+        self.reserve(1)?;
+        push_bytes(self, bytes)?;
+
+        Ok(Buffer {
+            value: unsafe { StackRef::adopt(self) },
+        })
+    }
+    /// Creates a new buffer of `len` zero bytes
+    pub fn create_buffer_zeroed(&self, len: usize) -> Result<Buffer> {
+        self.reserve(1)?;
+        push_buffer(self, len)?;
+
+        Ok(Buffer {
+            value: unsafe { StackRef::adopt(self) },
+        })
+    }
+    /// Fails if any buffer is borrowed, checked before anything that may run luau code
+    pub(crate) fn check_buffers(&self) -> Result<()> {
+        if !self.data().buffer_borrows.borrow().is_empty() {
+            return Err(Error::BorrowedBuffer);
+        }
+
+        Ok(())
+    }
+}
+
+/// Pushes a new zeroed buffer, returning a pointer to its bytes
+fn push_buffer(state: &LuauState, len: usize) -> Result<*mut u8> {
+    let l = state.as_ptr();
+
+    let mut ptr = null_mut();
+    unsafe { check(l, shim::lua_newbuffer(l, len, &mut ptr))? };
+
+    Ok(ptr.cast())
+}
+
+/// Pushes a new buffer with a copy of the bytes, the caller makes sure there is space on the stack
+pub(crate) fn push_bytes(state: &LuauState, bytes: &[u8]) -> Result<()> {
+    let ptr = push_buffer(state, bytes.len())?;
+    unsafe { ptr.copy_from_nonoverlapping(bytes.as_ptr(), bytes.len()) };
+
+    Ok(())
+}
+
+impl<'a> Buffer<'a> {
+    pub fn len(&self) -> usize {
+        self.raw().1
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Immutably borrows the bytes, failing if they are mutably borrowed
+    pub fn borrow(&self) -> Result<BufferRef<'_>> {
+        let (ptr, len) = self.raw();
+        let borrow = BorrowGuard::new(self.value.state(), ptr as usize, false)?;
+
+        Ok(BufferRef {
+            bytes: unsafe { slice::from_raw_parts(ptr, len) },
+            _borrow: borrow,
+        })
+    }
+    /// Mutably borrows the bytes, failing if they are borrowed
+    pub fn borrow_mut(&self) -> Result<BufferMut<'_>> {
+        let (ptr, len) = self.raw();
+        let borrow = BorrowGuard::new(self.value.state(), ptr as usize, true)?;
+
+        Ok(BufferMut {
+            bytes: unsafe { slice::from_raw_parts_mut(ptr, len) },
+            _borrow: borrow,
+        })
+    }
+    /// Copies the bytes out
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        Ok(self.borrow()?.to_vec())
+    }
+    fn raw(&self) -> (*mut u8, usize) {
+        let mut len = 0;
+        let ptr =
+            unsafe { lua_tobuffer(self.value.state().as_ptr(), self.value.index(), &mut len) };
+
+        (ptr.cast(), len)
+    }
+}
+
+/// Registers a borrow of the buffer at `key` in the state, like a `RefCell` shared by all handles to it
+struct BorrowGuard<'b> {
+    state: &'b LuauState,
+    key: usize,
+}
+
+impl<'b> BorrowGuard<'b> {
+    fn new(state: &'b LuauState, key: usize, exclusive: bool) -> Result<Self> {
+        let mut borrows = state.data().buffer_borrows.borrow_mut();
+        let count = borrows.entry(key).or_insert(0);
+
+        match (exclusive, *count) {
+            (_, -1) => return Err(Error::BufferBorrow),
+            (true, 1..) => return Err(Error::BufferBorrowMut),
+            (true, _) => *count = -1,
+            (false, _) => *count += 1,
+        }
+
+        Ok(Self { state, key })
+    }
+}
+
+impl Drop for BorrowGuard<'_> {
+    fn drop(&mut self) {
+        let mut borrows = self.state.data().buffer_borrows.borrow_mut();
+        let count = borrows.get_mut(&self.key).unwrap();
+
+        if *count > 1 {
+            *count -= 1;
+        } else {
+            borrows.remove(&self.key);
+        }
+    }
+}
+
+/// Immutably borrowed bytes of a [`Buffer`]
+pub struct BufferRef<'b> {
+    bytes: &'b [u8],
+    _borrow: BorrowGuard<'b>,
+}
+
+impl Deref for BufferRef<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.bytes
+    }
+}
+
+/// Mutably borrowed bytes of a [`Buffer`]
+pub struct BufferMut<'b> {
+    bytes: &'b mut [u8],
+    _borrow: BorrowGuard<'b>,
+}
+
+impl Deref for BufferMut<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.bytes
+    }
+}
+impl DerefMut for BufferMut<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.bytes
+    }
+}
+
+impl IntoLuau for Buffer<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        self.value.push(state)
+    }
+}
+impl IntoLuau for &Buffer<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        (&self.value).push(state)
+    }
+}
+impl<'a> FromLuau<'a> for Buffer<'a> {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        expect_type(&value, lua_Type::LUA_TBUFFER, "Buffer")?;
+
+        Ok(Buffer { value })
+    }
+}
+impl FromLuauArg for Buffer<'_> {
+    type Arg<'a> = Buffer<'a>;
+}
+
+/// Converted to a new buffer, types like `bytes::Bytes` can be passed with `&*bytes`
+impl IntoLuau for &[u8] {
+    fn push(self, state: &LuauState) -> Result<()> {
+        push_bytes(state, self)
+    }
+}
+
+impl PartialEq for Buffer<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        let l = self.value.state().as_ptr();
+
+        unsafe { lua_rawequal(l, self.value.index(), other.value.index()) != 0 }
+    }
+}
+
+impl Debug for Buffer<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ptr = unsafe { lua_topointer(self.value.state().as_ptr(), self.value.index()) };
+
+        write!(f, "<Luau Buffer {ptr:p} of {} bytes>", self.len())
+    }
+}
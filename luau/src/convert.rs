@@ -0,0 +1,528 @@
+use crate::{
+    buffer::{push_bytes, Buffer},
+    error::{check, Error, Result},
+    stack::StackRef,
+    state::LuauState,
+    table::Table,
+    value::MAX_SAFE_INTEGER,
+};
+use luau_sys::{
+    shim,
+    vm::{
+        lua_Type, lua_pushboolean, lua_pushnil, lua_pushnumber, lua_toboolean, lua_tolstring,
+        lua_tonumberx, lua_type,
+    },
+};
+use std::{
+    collections::HashMap,
+    ffi::c_int,
+    hash::{BuildHasher, Hash},
+    slice,
+};
+
+/// Types that can be pushed onto the luau stack
+pub trait IntoLuau {
+    /// Pushes the value on top of the stack of `state`
+    ///
+    /// The caller makes sure there is space for at least one value. On error the stack must be left unchanged.
+    fn push(self, state: &LuauState) -> Result<()>;
+    /// Pushes a `Vec` of values, as a table unless the type converts differently (`Vec<u8>` is a buffer)
+    #[doc(hidden)]
+    fn push_vec(values: Vec<Self>, state: &LuauState) -> Result<()>
+    where
+        Self: Sized,
+    {
+        let l = state.as_ptr();
+
+        push_table(state, values.len(), 0, || {
+            for (i, elem) in values.into_iter().enumerate() {
+                state.reserve(1)?;
+                elem.push(state)?;
+                unsafe { check(l, shim::lua_rawseti(l, -2, i as c_int + 1))? };
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Types that can be created from a luau value
+pub trait FromLuau<'a>: Sized {
+    /// Converts the value, taking ownership of its stack slot
+    fn from_luau(value: StackRef<'a>) -> Result<Self>;
+    /// Converts a value to a `Vec`, from a table unless the type converts differently (`Vec<u8>` is a buffer)
+    #[doc(hidden)]
+    fn from_vec(value: StackRef<'a>) -> Result<Vec<Self>> {
+        expect_type(&value, lua_Type::LUA_TTABLE, "Vec")?;
+        let table = Table { value };
+
+        (1..=table.len()).map(|i| table.raw_get(i)).collect()
+    }
+}
+
+/// Types that can be arguments of rust functions, see [`LuauState::create_function`]
+///
+/// A rust function is called with a temporary state, so its arguments can only borrow that state. `Arg<'a>` is
+/// the type converted for a state borrowed for `'a`: handles like [`Table`] are the same handle with lifetime
+/// `'a`, types that don't borrow the state are themselves. Implement it alongside [`FromLuau`] to take your own
+/// types as arguments.
+pub trait FromLuauArg {
+    type Arg<'a>: FromLuau<'a>;
+}
+
+/// Types that can be the arguments of rust functions, like [`FromLuauArg`] for [`FromLuauMulti`]
+pub trait FromLuauArgs {
+    type Args<'a>: FromLuauMulti<'a>;
+}
+
+/// Implements [`FromLuauArg`] for types that don't borrow the state
+macro_rules! impl_arg {
+    ($($t:ty),*) => {$(
+        impl FromLuauArg for $t {
+            type Arg<'a> = $t;
+        }
+    )*};
+}
+
+pub(crate) fn type_of(value: &StackRef) -> lua_Type {
+    lua_Type(unsafe { lua_type(value.state().as_ptr(), value.index()) } as _)
+}
+
+pub(crate) fn expect_type(value: &StackRef, ty: lua_Type, to: &'static str) -> Result<()> {
+    if type_of(value) != ty {
+        return Err(Error::FromLuau {
+            from: value.type_name(),
+            to,
+        });
+    }
+
+    Ok(())
+}
+
+// Handles
+//////////
+
+impl IntoLuau for StackRef<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        (&self).push(state)
+    }
+}
+impl IntoLuau for &StackRef<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        unsafe { self.push_copy(state) };
+
+        Ok(())
+    }
+}
+impl<'a> FromLuau<'a> for StackRef<'a> {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        Ok(value)
+    }
+}
+impl FromLuauArg for StackRef<'_> {
+    type Arg<'a> = StackRef<'a>;
+}
+
+// Nil
+//////
+
+impl<T: IntoLuau> IntoLuau for Option<T> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        match self {
+            Some(v) => v.push(state),
+            None => {
+                unsafe { lua_pushnil(state.as_ptr()) };
+
+                Ok(())
+            }
+        }
+    }
+}
+impl<'a, T: FromLuau<'a>> FromLuau<'a> for Option<T> {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        match type_of(&value) {
+            lua_Type::LUA_TNIL => Ok(None),
+            _ => T::from_luau(value).map(Some),
+        }
+    }
+}
+impl<T: FromLuauArg> FromLuauArg for Option<T> {
+    type Arg<'a> = Option<T::Arg<'a>>;
+}
+
+// Booleans
+///////////
+
+impl IntoLuau for bool {
+    fn push(self, state: &LuauState) -> Result<()> {
+        unsafe { lua_pushboolean(state.as_ptr(), self as c_int) };
+
+        Ok(())
+    }
+}
+impl<'a> FromLuau<'a> for bool {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        expect_type(&value, lua_Type::LUA_TBOOLEAN, "bool")?;
+
+        Ok(unsafe { lua_toboolean(value.state().as_ptr(), value.index()) } != 0)
+    }
+}
+impl_arg!(bool);
+
+// Numbers
+//////////
+
+impl IntoLuau for f64 {
+    fn push(self, state: &LuauState) -> Result<()> {
+        unsafe { lua_pushnumber(state.as_ptr(), self) };
+
+        Ok(())
+    }
+}
+impl<'a> FromLuau<'a> for f64 {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        expect_type(&value, lua_Type::LUA_TNUMBER, "f64")?;
+
+        Ok(unsafe { lua_tonumberx(value.state().as_ptr(), value.index(), std::ptr::null_mut()) })
+    }
+}
+
+impl IntoLuau for f32 {
+    fn push(self, state: &LuauState) -> Result<()> {
+        (self as f64).push(state)
+    }
+}
+impl<'a> FromLuau<'a> for f32 {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        expect_type(&value, lua_Type::LUA_TNUMBER, "f32")?;
+
+        f64::from_luau(value).map(|n| n as f32)
+    }
+}
+impl_arg!(f64, f32);
+
+// Integers are only converted if they can be represented exactly
+macro_rules! impl_integer {
+    ($t:ty, { $($into:item)* }, { $($from:item)* }) => {
+        impl IntoLuau for $t {
+            fn push(self, state: &LuauState) -> Result<()> {
+                let n = self as f64;
+
+                if n.abs() > MAX_SAFE_INTEGER {
+                    return Err(Error::ToLuau {
+                        from: stringify!($t),
+                        to: "number",
+                    });
+                }
+
+                n.push(state)
+            }
+            $($into)*
+        }
+        impl<'a> FromLuau<'a> for $t {
+            fn from_luau(value: StackRef<'a>) -> Result<Self> {
+                let err = Error::FromLuau {
+                    from: value.type_name(),
+                    to: stringify!($t),
+                };
+
+                expect_type(&value, lua_Type::LUA_TNUMBER, stringify!($t))?;
+                let n = f64::from_luau(value)?;
+
+                if n.fract() != 0.0
+                    || n.abs() > MAX_SAFE_INTEGER
+                    || n < <$t>::MIN as f64
+                    || n > <$t>::MAX as f64
+                {
+                    return Err(err);
+                }
+
+                Ok(n as $t)
+            }
+            $($from)*
+        }
+        impl_arg!($t);
+    };
+    ($($t:ty),*) => {$(
+        impl_integer!($t, {}, {});
+    )*};
+}
+impl_integer!(i8, i16, i32, i64, isize, u16, u32, u64, usize);
+// bytes are converted to and from buffers
+impl_integer!(
+    u8,
+    {
+        fn push_vec(values: Vec<Self>, state: &LuauState) -> Result<()> {
+            push_bytes(state, &values)
+        }
+    },
+    {
+        fn from_vec(value: StackRef<'a>) -> Result<Vec<Self>> {
+            Buffer::from_luau(value)?.to_vec()
+        }
+    }
+);
+
+// Strings
+//////////
+
+impl IntoLuau for &str {
+    fn push(self, state: &LuauState) -> Result<()> {
+        let l = state.as_ptr();
+
+        unsafe {
+            check(
+                l,
+                shim::lua_pushlstring(l, self.as_ptr().cast(), self.len()),
+            )
+        }
+    }
+}
+impl IntoLuau for String {
+    fn push(self, state: &LuauState) -> Result<()> {
+        self.as_str().push(state)
+    }
+}
+impl IntoLuau for &String {
+    fn push(self, state: &LuauState) -> Result<()> {
+        self.as_str().push(state)
+    }
+}
+impl<'a> FromLuau<'a> for String {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        expect_type(&value, lua_Type::LUA_TSTRING, "String")?;
+
+        let bytes = unsafe {
+            let mut len = 0;
+            let ptr = lua_tolstring(value.state().as_ptr(), value.index(), &mut len);
+
+            slice::from_raw_parts(ptr as *const u8, len)
+        };
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| Error::FromLuau {
+            from: "string",
+            to: "String",
+        })
+    }
+}
+impl_arg!(String);
+
+// Collections
+//////////////
+
+/// Pushes a new table and fills it, popping it again on error
+fn push_table(
+    state: &LuauState,
+    narr: usize,
+    nrec: usize,
+    fill: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    let l = state.as_ptr();
+    let guard = state.stack_guard(0)?;
+
+    unsafe {
+        check(
+            l,
+            shim::lua_createtable(
+                l,
+                narr.try_into().unwrap_or(c_int::MAX),
+                nrec.try_into().unwrap_or(c_int::MAX),
+            ),
+        )?
+    };
+
+    fill()?;
+    guard.commit();
+
+    Ok(())
+}
+
+/// Converted to a table with the elements in the array part, except `Vec<u8>` which is converted to a buffer
+impl<T: IntoLuau> IntoLuau for Vec<T> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        T::push_vec(self, state)
+    }
+}
+/// Converted from the array part of a table, except `Vec<u8>` which is converted from a buffer
+impl<'a, T: FromLuau<'a>> FromLuau<'a> for Vec<T> {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        T::from_vec(value)
+    }
+}
+impl<T: FromLuauArg> FromLuauArg for Vec<T> {
+    type Arg<'a> = Vec<T::Arg<'a>>;
+}
+
+impl<K: IntoLuau, V: IntoLuau, S> IntoLuau for HashMap<K, V, S> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        let l = state.as_ptr();
+
+        push_table(state, 0, self.len(), || {
+            for (k, v) in self {
+                let guard = state.stack_guard(2)?;
+                k.push(state)?;
+                v.push(state)?;
+                guard.commit();
+                unsafe { check(l, shim::lua_rawset(l, -3))? };
+            }
+
+            Ok(())
+        })
+    }
+}
+impl<'a, K, V, S> FromLuau<'a> for HashMap<K, V, S>
+where
+    K: FromLuau<'a> + Eq + Hash,
+    V: FromLuau<'a>,
+    S: BuildHasher + Default,
+{
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        expect_type(&value, lua_Type::LUA_TTABLE, "HashMap")?;
+        let table = Table { value };
+
+        table.pairs().collect()
+    }
+}
+/// The keys can't borrow the state
+impl<K, V, S> FromLuauArg for HashMap<K, V, S>
+where
+    K: for<'a> FromLuau<'a> + Eq + Hash,
+    V: FromLuauArg,
+    S: BuildHasher + Default,
+{
+    type Arg<'a> = HashMap<K, V::Arg<'a>, S>;
+}
+
+/// Types that can be pushed as any number of values, such as function arguments
+pub trait IntoLuauMulti {
+    /// Pushes all the values on top of the stack of `state`, returning how many were pushed
+    ///
+    /// Implementations reserve the stack space themselves. On error the stack must be left unchanged.
+    fn push_multi(self, state: &LuauState) -> Result<c_int>;
+
+    /// Whether a rust function returning these values yields them instead, only [`Yield`](crate::thread::Yield) does
+    #[doc(hidden)]
+    fn yields(&self) -> bool {
+        false
+    }
+}
+
+/// Types that can be created from any number of luau values, such as function results
+pub trait FromLuauMulti<'a>: Sized {
+    /// Converts the values, taking ownership of their stack slots
+    fn from_luau_multi(state: &'a LuauState, values: Vec<StackRef<'a>>) -> Result<Self>;
+}
+
+impl<T: IntoLuau> IntoLuauMulti for T {
+    fn push_multi(self, state: &LuauState) -> Result<c_int> {
+        state.reserve(1)?;
+        self.push(state)?;
+
+        Ok(1)
+    }
+}
+impl<'a, T: FromLuau<'a>> FromLuauMulti<'a> for T {
+    fn from_luau_multi(state: &'a LuauState, values: Vec<StackRef<'a>>) -> Result<Self> {
+        // extra values are dropped
+        match values.into_iter().next() {
+            Some(value) => T::from_luau(value),
+            None => T::from_luau(StackRef::nil(state)?),
+        }
+    }
+}
+
+impl<T: FromLuauArg> FromLuauArgs for T {
+    type Args<'a> = T::Arg<'a>;
+}
+
+impl IntoLuauMulti for () {
+    fn push_multi(self, _state: &LuauState) -> Result<c_int> {
+        Ok(0)
+    }
+}
+impl<'a> FromLuauMulti<'a> for () {
+    fn from_luau_multi(_state: &'a LuauState, _values: Vec<StackRef<'a>>) -> Result<Self> {
+        Ok(())
+    }
+}
+impl FromLuauArgs for () {
+    type Args<'a> = ();
+}
+
+/// Any number of values of the same type, for variadic arguments and results
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Variadic<T>(pub Vec<T>);
+
+impl<T: IntoLuau> IntoLuauMulti for Variadic<T> {
+    fn push_multi(self, state: &LuauState) -> Result<c_int> {
+        let n = self.0.len().try_into().map_err(|_| Error::StackOverflow)?;
+
+        let guard = state.stack_guard(n)?;
+        for value in self.0 {
+            value.push(state)?;
+        }
+        guard.commit();
+
+        Ok(n)
+    }
+}
+impl<'a, T: FromLuau<'a>> FromLuauMulti<'a> for Variadic<T> {
+    fn from_luau_multi(_state: &'a LuauState, values: Vec<StackRef<'a>>) -> Result<Self> {
+        values
+            .into_iter()
+            .map(T::from_luau)
+            .collect::<Result<_>>()
+            .map(Variadic)
+    }
+}
+impl<T: FromLuauArg> FromLuauArgs for Variadic<T> {
+    type Args<'a> = Variadic<T::Arg<'a>>;
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: IntoLuau),+> IntoLuauMulti for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn push_multi(self, state: &LuauState) -> Result<c_int> {
+                let ($($name,)+) = self;
+                let n = [$(stringify!($name)),+].len() as c_int;
+
+                let guard = state.stack_guard(n)?;
+                $(
+                    $name.push(state)?;
+                )+
+                guard.commit();
+
+                Ok(n)
+            }
+        }
+        impl<'a, $($name: FromLuau<'a>),+> FromLuauMulti<'a> for ($($name,)+) {
+            fn from_luau_multi(state: &'a LuauState, values: Vec<StackRef<'a>>) -> Result<Self> {
+                let mut values = values.into_iter();
+
+                // missing values are nil, extra values are dropped
+                Ok(($(
+                    $name::from_luau(match values.next() {
+                        Some(value) => value,
+                        None => StackRef::nil(state)?,
+                    })?,
+                )+))
+            }
+        }
+        impl<$($name: FromLuauArg),+> FromLuauArgs for ($($name,)+) {
+            type Args<'a> = ($($name::Arg<'a>,)+);
+        }
+    };
+}
+impl_tuple!(A);
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+impl_tuple!(A, B, C, D, E);
+impl_tuple!(A, B, C, D, E, F);
+impl_tuple!(A, B, C, D, E, F, G);
+impl_tuple!(A, B, C, D, E, F, G, H);
+impl_tuple!(A, B, C, D, E, F, G, H, I);
+impl_tuple!(A, B, C, D, E, F, G, H, I, J);
+impl_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+impl_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
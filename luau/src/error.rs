@@ -0,0 +1,319 @@
+use crate::{ffi::lua_pop, load::LoadError};
+use luau_compiler::CompileError;
+use luau_sys::{
+    shim::{self, RAISE_ERROR},
+    vm::{
+        lua_State, lua_Status, lua_Type, lua_settop, lua_setuserdatadtor, lua_setuserdatametatable,
+        lua_tolstring, lua_touserdatatagged, lua_type, lua_typename, lua_userdatatag,
+        LUA_UTAG_LIMIT,
+    },
+};
+use std::{
+    any::Any,
+    error,
+    ffi::{c_int, c_void, CStr},
+    fmt::Display,
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr::{self, null_mut},
+    slice,
+};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors that can happen when interacting with a [`LuauState`](crate::state::LuauState)
+#[derive(Debug)]
+pub enum Error {
+    /// An error raised while running luau code
+    Runtime {
+        message: String,
+        /// Stack trace of the luau code where the error was raised, if it was captured
+        traceback: Option<String>,
+    },
+    /// The allocator refused to give more memory (`LUA_ERRMEM`)
+    Memory,
+    /// The message handler raised an error itself (`LUA_ERRERR`), with the error message
+    ErrorHandler(String),
+    /// There is no more space on the luau stack
+    StackOverflow,
+    /// Attempted to modify a readonly table
+    Readonly,
+    /// Source code failed to compile
+    Compile(CompileError),
+    /// Bytecode failed to load
+    Load(LoadError),
+    /// A rust value could not be converted to the requested luau type
+    ToLuau {
+        from: &'static str,
+        to: &'static str,
+    },
+    /// A luau value could not be converted to the requested rust type, `from` is the luau type of the value
+    FromLuau {
+        from: &'static str,
+        to: &'static str,
+    },
+    /// A rust callback returned an error
+    Callback(Box<dyn error::Error + Send + Sync>),
+    /// A rust callback panicked, with the panic message
+    Panic(String),
+    /// The userdata is already mutably borrowed, with the name of the type
+    UserDataBorrow(&'static str),
+    /// The userdata is already borrowed, with the name of the type
+    UserDataBorrowMut(&'static str),
+    /// Luau ran out of userdata tags for new rust types
+    TooManyUserDataTypes,
+    /// Luau ran out of light userdata tags for new rust types
+    TooManyLightTypes,
+    /// Accessed a field that the userdata type doesn't declare
+    UnknownField {
+        type_name: &'static str,
+        field: String,
+    },
+    /// The interrupt callback stopped the running code, with the reason
+    Interrupted(&'static str),
+    /// The interrupt callback used the state during a garbage collection step
+    GcStep,
+    /// A global declared mutable in the compiler options is frozen by the sandbox
+    ReadonlyGlobal(String),
+    /// The buffer is already mutably borrowed
+    BufferBorrow,
+    /// The buffer is already borrowed
+    BufferBorrowMut,
+    /// Luau code can't run while the bytes of a buffer are borrowed
+    BorrowedBuffer,
+    /// An [`OwnedRef`](crate::owned::OwnedRef) was used with a state other than the one it was created with
+    ForeignRef,
+}
+
+impl Error {
+    /// Wraps an error returned by a callback, errors from this crate are kept as they are
+    pub(crate) fn from_callback(error: Box<dyn error::Error + Send + Sync>) -> Self {
+        match error.downcast::<Error>() {
+            Ok(e) => *e,
+            Err(e) => Error::Callback(e),
+        }
+    }
+    /// Converts the error object on top of the stack and pops it
+    pub(crate) unsafe fn pop(l: *mut lua_State, status: lua_Status) -> Self {
+        // message handlers run for memory errors too, whatever they turned the error into
+        if status == lua_Status::LUA_ERRMEM {
+            unsafe { lua_pop(l, 1) };
+            return Error::Memory;
+        }
+
+        if unsafe { lua_userdatatag(l, -1) } == ERROR_TAG {
+            let wrapped =
+                unsafe { &mut *(lua_touserdatatagged(l, -1, ERROR_TAG) as *mut WrappedError) };
+
+            // the same error object may be caught more than once, only the first time gets the original
+            let error = wrapped
+                .error
+                .take()
+                .unwrap_or_else(|| Error::runtime(wrapped.message.clone()));
+
+            unsafe { lua_pop(l, 1) };
+            return error;
+        }
+
+        match status {
+            lua_Status::LUA_ERRERR => Error::ErrorHandler(unsafe { pop_message(l) }),
+            lua_Status::LUA_ERRSYNTAX => Error::Load(LoadError::Invalid(unsafe { pop_message(l) })),
+            _ => Error::runtime(unsafe { pop_message(l) }),
+        }
+    }
+    /// A runtime error without a traceback
+    pub(crate) fn runtime(message: String) -> Self {
+        Error::Runtime {
+            message,
+            traceback: None,
+        }
+    }
+}
+
+/// Pops the error object on top of the stack, converting it to a message
+pub(crate) unsafe fn pop_message(l: *mut lua_State) -> String {
+    let message = unsafe { error_message(l, -1) };
+    unsafe { lua_pop(l, 1) };
+
+    message
+}
+
+/// Userdata tag reserved for rust errors travelling through luau
+pub(crate) const ERROR_TAG: c_int = LUA_UTAG_LIMIT as c_int - 1;
+
+/// A rust error travelling through luau as an error object
+struct WrappedError {
+    /// taken out once the error object is caught in rust
+    error: Option<Error>,
+    /// for `tostring` and for catching the same error object again
+    message: String,
+}
+
+/// Sets up the destructor and metatable for wrapped errors, called once when creating the state
+pub(crate) unsafe fn register_error_type(l: *mut lua_State) -> Result<()> {
+    unsafe extern "C" fn dtor(_l: *mut lua_State, ud: *mut c_void) {
+        // can't unwind into luau
+        let _ = catch_unwind(AssertUnwindSafe(|| unsafe {
+            ptr::drop_in_place(ud as *mut WrappedError)
+        }));
+    }
+    unsafe extern "C" fn tostring(l: *mut lua_State) -> c_int {
+        let wrapped = unsafe { lua_touserdatatagged(l, 1, ERROR_TAG) as *const WrappedError };
+        let message = unsafe { &(*wrapped).message };
+
+        // the error object is left on the stack if this fails
+        match unsafe { shim::lua_pushlstring(l, message.as_ptr().cast(), message.len()) } {
+            lua_Status::LUA_OK => 1,
+            _ => RAISE_ERROR,
+        }
+    }
+
+    unsafe {
+        lua_setuserdatadtor(l, ERROR_TAG, Some(dtor));
+
+        check(l, shim::lua_createtable(l, 0, 1))?;
+        check(
+            l,
+            shim::lua_pushrustclosure(l, Some(tostring), c"tostring".as_ptr(), 0),
+        )?;
+        check(l, shim::lua_rawsetfield(l, -2, c"__tostring".as_ptr()))?;
+        lua_setuserdatametatable(l, ERROR_TAG);
+    }
+
+    Ok(())
+}
+
+/// Pushes the error as an error object
+///
+/// If that fails, the memory error object is left on the stack instead
+pub(crate) unsafe fn push_error(l: *mut lua_State, error: Error) {
+    let message = error.to_string();
+
+    let mut ud = null_mut();
+    let status = unsafe {
+        shim::lua_newuserdatataggedwithmetatable(l, size_of::<WrappedError>(), ERROR_TAG, &mut ud)
+    };
+
+    if status == lua_Status::LUA_OK {
+        let wrapped = WrappedError {
+            error: Some(error),
+            message,
+        };
+        unsafe { ptr::write(ud as *mut WrappedError, wrapped) };
+    }
+}
+
+/// Prepares the error to be raised by the trampoline, returning the value that the
+/// rust function must return
+pub(crate) unsafe fn raise(l: *mut lua_State, error: Error) -> c_int {
+    unsafe {
+        // make space for the error object, everything on the stack is discarded anyway
+        lua_settop(l, 0);
+        push_error(l, error);
+    }
+
+    RAISE_ERROR
+}
+
+/// Extracts the message from a panic payload
+pub(crate) fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "Box<dyn Any>".to_owned()
+    }
+}
+
+/// Converts the status returned by a shim function to a result, popping the error object if any
+pub(crate) unsafe fn check(l: *mut lua_State, status: lua_Status) -> Result<()> {
+    if status == lua_Status::LUA_OK {
+        Ok(())
+    } else {
+        Err(unsafe { Error::pop(l, status) })
+    }
+}
+
+/// Describes an arbitrary error object, without calling any metamethods or allocating inside luau
+unsafe fn error_message(l: *mut lua_State, idx: c_int) -> String {
+    let ty = unsafe { lua_type(l, idx) };
+
+    if ty == lua_Type::LUA_TSTRING.0 as c_int {
+        let mut len = 0;
+        let ptr = unsafe { lua_tolstring(l, idx, &mut len) };
+        let bytes = unsafe { slice::from_raw_parts(ptr as *const u8, len) };
+
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        let type_name = unsafe { CStr::from_ptr(lua_typename(l, ty)) };
+
+        format!("(error object is a {} value)", type_name.to_string_lossy())
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Runtime { message, traceback } => {
+                write!(f, "runtime error: {message}")?;
+                if let Some(traceback) = traceback {
+                    write!(f, "\nstack traceback:\n{traceback}")?;
+                }
+
+                Ok(())
+            }
+            Error::Memory => write!(f, "not enough memory"),
+            Error::ErrorHandler(msg) => write!(f, "error in error handling: {msg}"),
+            Error::StackOverflow => write!(f, "luau stack overflow"),
+            Error::Readonly => write!(f, "attempt to modify a readonly table"),
+            Error::Compile(e) => Display::fmt(e, f),
+            Error::Load(e) => Display::fmt(e, f),
+            Error::ToLuau { from, to } => write!(f, "cannot convert {from} to luau {to}"),
+            Error::FromLuau { from, to } => write!(f, "cannot convert luau {from} to {to}"),
+            Error::Callback(e) => write!(f, "callback error: {e}"),
+            Error::Panic(msg) => write!(f, "rust panic: {msg}"),
+            Error::UserDataBorrow(name) => write!(f, "{name} is already mutably borrowed"),
+            Error::UserDataBorrowMut(name) => write!(f, "{name} is already borrowed"),
+            Error::TooManyUserDataTypes => write!(f, "too many userdata types"),
+            Error::TooManyLightTypes => write!(f, "too many light userdata types"),
+            Error::UnknownField { type_name, field } => {
+                write!(f, "{type_name} has no field '{field}'")
+            }
+            Error::Interrupted(reason) => write!(f, "execution interrupted: {reason}"),
+            Error::GcStep => write!(f, "cannot use the state during a garbage collection step"),
+            Error::ReadonlyGlobal(name) => {
+                write!(
+                    f,
+                    "global '{name}' is readonly in the sandbox and can't be mutable"
+                )
+            }
+            Error::BufferBorrow => write!(f, "buffer is already mutably borrowed"),
+            Error::BufferBorrowMut => write!(f, "buffer is already borrowed"),
+            Error::BorrowedBuffer => write!(f, "cannot run luau code while a buffer is borrowed"),
+            Error::ForeignRef => write!(f, "reference belongs to a different state"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Compile(e) => Some(e),
+            Error::Load(e) => Some(e),
+            Error::Callback(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<CompileError> for Error {
+    fn from(value: CompileError) -> Self {
+        Error::Compile(value)
+    }
+}
+
+impl From<LoadError> for Error {
+    fn from(value: LoadError) -> Self {
+        Error::Load(value)
+    }
+}
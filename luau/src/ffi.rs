@@ -0,0 +1,12 @@
+//! Rust versions of the function-like macros from `lua.h`, which bindgen can't generate
+
+use luau_sys::vm::{lua_State, lua_settop, LUA_GLOBALSINDEX};
+use std::ffi::c_int;
+
+pub(crate) unsafe fn lua_pop(l: *mut lua_State, n: c_int) {
+    unsafe { lua_settop(l, -n - 1) }
+}
+
+pub(crate) const fn lua_upvalueindex(i: c_int) -> c_int {
+    LUA_GLOBALSINDEX - i
+}
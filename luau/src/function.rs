@@ -0,0 +1,422 @@
+use crate::{
+    convert::{
+        expect_type, FromLuau, FromLuauArg, FromLuauArgs, FromLuauMulti, IntoLuau, IntoLuauMulti,
+    },
+    error::{check, panic_message, push_error, raise, Error, Result},
+    ffi::{lua_pop, lua_upvalueindex},
+    stack::StackRef,
+    state::LuauState,
+    thread::Yield,
+    userdata::UserData,
+};
+use luau_sys::{
+    shim,
+    vm::{
+        lua_Debug, lua_State, lua_Status, lua_Type, lua_getinfo, lua_gettop, lua_insert, lua_pcall,
+        lua_pushvalue, lua_remove, lua_topointer, lua_touserdata, LUA_MULTRET, LUA_REGISTRYINDEX,
+    },
+};
+use std::{
+    error,
+    ffi::{c_int, c_void, CStr},
+    fmt::Debug,
+    mem,
+    panic::{catch_unwind, AssertUnwindSafe},
+    pin::Pin,
+    ptr::{self, null, null_mut},
+};
+
+/// Handle to a luau function
+pub struct Function<'a> {
+    pub(crate) value: StackRef<'a>,
+}
+
+impl LuauState {
+    /// Creates a luau function that calls the given rust closure
+    ///
+    /// Inside the closure, the state is a temporary handle to the luau thread that called the function.
+    /// Returned errors are raised as luau errors, and can be caught back in rust unchanged. Panics are caught
+    /// and raised as [`Error::Panic`].
+    ///
+    /// Arguments that borrow the state, like [`Table`](crate::table::Table), borrow the temporary state and
+    /// can't outlive the call.
+    pub fn create_function<A, R, E, F>(&self, f: F) -> Result<Function>
+    where
+        A: FromLuauArgs,
+        R: IntoLuauMulti,
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+        F: Fn(&LuauState, A) -> Result<R, E>
+            + for<'a> Fn(&'a LuauState, A::Args<'a>) -> Result<R, E>
+            + Send
+            + 'static,
+    {
+        self.reserve(1)?;
+        wrap(f).push(self)?;
+
+        Ok(Function {
+            value: unsafe { StackRef::adopt(self) },
+        })
+    }
+    /// Creates a luau function that runs an async rust closure
+    ///
+    /// Calling it yields the luau thread until the returned future completes, after which its results are
+    /// returned to luau, or its error raised. So it only works when called from a thread driven by
+    /// [`Thread::into_future`](crate::thread::Thread::into_future), which polls the future.
+    ///
+    /// The arguments are moved into the future, so they can't borrow the state.
+    pub fn create_async_function<A, R, E, F, Fut>(&self, f: F) -> Result<Function>
+    where
+        A: for<'a> FromLuauArgs<Args<'a> = A>,
+        R: IntoLuauMulti + Send + 'static,
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+        F: Fn(A) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<R, E>> + Send + 'static,
+    {
+        self.create_function(move |_, args: A| {
+            let future = f(args);
+            let call: AsyncCall = Box::pin(async move {
+                let results = future.await.map_err(|e| Error::from_callback(e.into()))?;
+
+                Ok(AsyncResults(Box::new(move |state| {
+                    results.push_multi(state)
+                })))
+            });
+
+            Ok::<_, Error>(Yield(PendingCall(Some(call))))
+        })
+    }
+    /// Pushes a function calling `f`, which works with the stack of the calling thread directly
+    ///
+    /// The arguments are left untracked on the stack, and `f` returns the number of results it pushed.
+    /// The `nup` values on top of the stack become upvalues, starting at `lua_upvalueindex(3)`.
+    /// They are popped even if this fails.
+    pub(crate) fn push_raw_function<F>(&self, f: F, nup: c_int) -> Result<()>
+    where
+        F: Fn(&LuauState) -> Result<c_int> + Send + 'static,
+    {
+        unsafe extern "C" fn dtor<F>(ud: *mut c_void) {
+            // can't unwind into luau
+            let _ = catch_unwind(AssertUnwindSafe(|| unsafe {
+                drop(Box::from_raw(*(ud as *mut *mut F)))
+            }));
+        }
+        unsafe extern "C" fn call<F>(l: *mut lua_State) -> c_int
+        where
+            F: Fn(&LuauState) -> Result<c_int> + Send + 'static,
+        {
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                // first upvalue is the trampoline's
+                let f = unsafe { &**(lua_touserdata(l, lua_upvalueindex(2)) as *const *mut F) };
+
+                let state = unsafe { LuauState::borrowed(l) };
+                f(&state)
+            }));
+
+            match result {
+                Ok(Ok(nresults)) => nresults,
+                Ok(Err(e)) => unsafe { raise(l, e) },
+                Err(payload) => unsafe { raise(l, Error::Panic(panic_message(payload))) },
+            }
+        }
+
+        let l = self.as_ptr();
+
+        // the boxed closure and the trampoline's upvalue
+        if let Err(e) = self.reserve(2) {
+            unsafe { lua_pop(l, nup) };
+            return Err(e);
+        }
+
+        let mut ud = null_mut();
+        unsafe {
+            if let Err(e) = check(
+                l,
+                shim::lua_newuserdatadtor(l, size_of::<*mut F>(), Some(dtor::<F>), &mut ud),
+            ) {
+                lua_pop(l, nup);
+                return Err(e);
+            }
+            ptr::write(ud as *mut *mut F, Box::into_raw(Box::new(f)));
+            lua_insert(l, -(nup + 1));
+
+            check(
+                l,
+                shim::lua_pushrustclosure(l, Some(call::<F>), null(), nup + 1),
+            )
+        }
+    }
+}
+
+/// Wraps a rust closure so that it becomes a new luau function when pushed
+///
+/// Unlike [`LuauState::create_function`] this doesn't need a state, so it's useful for returning functions
+/// from other rust functions.
+pub fn wrap<A, R, E, F>(f: F) -> impl IntoLuau
+where
+    A: FromLuauArgs,
+    R: IntoLuauMulti,
+    E: Into<Box<dyn error::Error + Send + Sync>>,
+    F: Fn(&LuauState, A) -> Result<R, E>
+        + for<'a> Fn(&'a LuauState, A::Args<'a>) -> Result<R, E>
+        + Send
+        + 'static,
+{
+    struct Wrapped<F>(F);
+
+    impl<F: Fn(&LuauState) -> Result<c_int> + Send + 'static> IntoLuau for Wrapped<F> {
+        fn push(self, state: &LuauState) -> Result<()> {
+            state.push_raw_function(self.0, 0)
+        }
+    }
+
+    Wrapped(move |state: &LuauState| {
+        let nargs = unsafe { lua_gettop(state.as_ptr()) };
+        let values = unsafe { StackRef::adopt_many(state, nargs) };
+        let args = <A::Args<'_> as FromLuauMulti>::from_luau_multi(state, values)?;
+
+        let results = call(&f, state, args).map_err(|e| Error::from_callback(e.into()))?;
+
+        push_results(state, results)
+    })
+}
+
+/// Calls `f`, picking the `Fn` impl that takes the arguments for the state over the one that takes `A`
+fn call<'a, A, R>(f: &impl Fn(&'a LuauState, A) -> R, state: &'a LuauState, args: A) -> R {
+    f(state, args)
+}
+
+/// Pushes the values returned by a rust function, returning what the function must return
+///
+/// That is the number of values, unless they are to be yielded.
+pub(crate) fn push_results<R: IntoLuauMulti>(state: &LuauState, results: R) -> Result<c_int> {
+    let yields = results.yields();
+    let nresults = results.push_multi(state)?;
+
+    if !yields {
+        return Ok(nresults);
+    }
+
+    let l = state.as_ptr();
+    let mut ret = 0;
+    unsafe { check(l, shim::lua_yield(l, nresults, &mut ret))? };
+
+    Ok(ret)
+}
+
+/// The future of a running async rust function
+pub(crate) type AsyncCall = Pin<Box<dyn Future<Output = Result<AsyncResults>> + Send>>;
+
+/// Pushes the results of a completed async rust function
+pub(crate) struct AsyncResults(Box<dyn FnOnce(&LuauState) -> Result<c_int> + Send>);
+
+impl IntoLuauMulti for AsyncResults {
+    fn push_multi(self, state: &LuauState) -> Result<c_int> {
+        (self.0)(state)
+    }
+}
+
+/// Yielded by async rust functions, for the driver of the thread to take the future out of
+pub(crate) struct PendingCall(pub(crate) Option<AsyncCall>);
+
+impl UserData for PendingCall {
+    const NAME: &'static str = "PendingCall";
+}
+
+impl<'a> Function<'a> {
+    /// Calls the function in protected mode, returning any errors raised
+    ///
+    /// Luau errors are returned as [`Error::Runtime`] with the traceback of where they were raised.
+    pub fn call<A: IntoLuauMulti, R: FromLuauMulti<'a>>(&self, args: A) -> Result<R> {
+        self.call_inner(args, |state| {
+            let l = state.as_ptr();
+            let mut ty = 0;
+
+            unsafe {
+                check(
+                    l,
+                    shim::lua_rawgetfield(
+                        l,
+                        LUA_REGISTRYINDEX,
+                        TRACEBACK_HANDLER.as_ptr(),
+                        &mut ty,
+                    ),
+                )
+            }
+        })
+    }
+    /// Calls the function in protected mode, with `handler` as the message handler
+    ///
+    /// The handler is called with any error raised, before the stack is unwound, so the state can be
+    /// inspected (for example with [`LuauState::traceback`]). Whatever it returns is returned from this function,
+    /// except for memory errors which are always returned as [`Error::Memory`].
+    pub fn call_with_handler<A, R, H>(&self, args: A, handler: H) -> Result<R>
+    where
+        A: IntoLuauMulti,
+        R: FromLuauMulti<'a>,
+        H: Fn(&LuauState, Error) -> Error + Send + 'static,
+    {
+        self.call_inner(args, |state| {
+            state.push_raw_function(
+                move |state| {
+                    let l = state.as_ptr();
+                    let error = unsafe { handler_error(l) };
+
+                    // the error object stays below the handles that the handler creates
+                    let state = unsafe { LuauState::borrowed_at(l, lua_gettop(l)) };
+                    let error = handler(&state, error);
+
+                    unsafe { push_error(l, error) };
+
+                    Ok(1)
+                },
+                0,
+            )
+        })
+    }
+    /// Calls the function with the message handler pushed by `push_handler`
+    fn call_inner<A: IntoLuauMulti, R: FromLuauMulti<'a>>(
+        &self,
+        args: A,
+        push_handler: impl FnOnce(&LuauState) -> Result<()>,
+    ) -> Result<R> {
+        let state = self.value.state();
+        let l = state.as_ptr();
+
+        state.check_buffers()?;
+
+        // the handler and the function
+        let guard = state.stack_guard(2)?;
+        let top = guard.top();
+
+        push_handler(state)?;
+        unsafe { self.value.push_copy(state) };
+        let nargs = args.push_multi(state)?;
+
+        let status = lua_Status(unsafe { lua_pcall(l, nargs, LUA_MULTRET, top + 1) } as _);
+        if status != lua_Status::LUA_OK {
+            // the guard pops the handler
+            return Err(unsafe { Error::pop(l, status) });
+        }
+
+        unsafe { lua_remove(l, top + 1) };
+
+        let nresults = unsafe { lua_gettop(l) } - top;
+        R::from_luau_multi(state, unsafe { StackRef::adopt_many(state, nresults) })
+    }
+}
+
+impl LuauState {
+    /// Describes the call stack of the thread, one function per line, like `debug.traceback`
+    ///
+    /// Starts at the function that called the running rust function, which is where the error was raised
+    /// when called inside a message handler.
+    pub fn traceback(&self) -> String {
+        unsafe { traceback(self.as_ptr(), 1) }
+    }
+}
+
+/// Describes the call stack of the thread, starting at `level` (0 being the running function)
+pub(crate) unsafe fn traceback(l: *mut lua_State, level: c_int) -> String {
+    let mut lines = Vec::new();
+
+    let mut ar: lua_Debug = unsafe { mem::zeroed() };
+    let mut level = level;
+    while unsafe { lua_getinfo(l, level, c"sln".as_ptr(), &mut ar) } != 0 {
+        let mut line = unsafe { CStr::from_ptr(ar.short_src) }
+            .to_string_lossy()
+            .into_owned();
+
+        if ar.currentline > 0 {
+            line += &format!(":{}", ar.currentline);
+        }
+        if !ar.name.is_null() {
+            line += &format!(
+                " function {}",
+                unsafe { CStr::from_ptr(ar.name) }.to_string_lossy()
+            );
+        }
+
+        lines.push(line);
+        level += 1;
+    }
+
+    lines.join("\n")
+}
+
+/// Registry field with the default message handler
+const TRACEBACK_HANDLER: &CStr = c"luau-rs.traceback";
+
+/// Sets up the default message handler, called once when creating the state
+pub(crate) unsafe fn register_traceback_handler(l: *mut lua_State) -> Result<()> {
+    unsafe extern "C" fn handler(l: *mut lua_State) -> c_int {
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let state = unsafe { LuauState::borrowed(l) };
+
+            match unsafe { handler_error(l) } {
+                Error::Runtime { message, .. } => Error::Runtime {
+                    message,
+                    traceback: Some(state.traceback()),
+                },
+                e => e,
+            }
+        }));
+
+        // on a panic the original error object is left in place
+        if let Ok(error) = result {
+            unsafe { push_error(l, error) };
+        }
+
+        1
+    }
+
+    unsafe {
+        check(
+            l,
+            shim::lua_pushrustclosure(l, Some(handler), c"traceback".as_ptr(), 0),
+        )?;
+        check(
+            l,
+            shim::lua_rawsetfield(l, LUA_REGISTRYINDEX, TRACEBACK_HANDLER.as_ptr()),
+        )
+    }
+}
+
+/// Converts the error object passed to a message handler
+///
+/// The object is left on the stack, although a wrapped rust error is taken out of it. Memory errors are run
+/// through the handler too, as a runtime error with the message "not enough memory" since the handler can't tell
+/// them apart from errors with that message. The status of the call turns them into [`Error::Memory`].
+unsafe fn handler_error(l: *mut lua_State) -> Error {
+    unsafe { lua_pushvalue(l, 1) };
+
+    unsafe { Error::pop(l, lua_Status::LUA_ERRRUN) }
+}
+
+impl IntoLuau for Function<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        self.value.push(state)
+    }
+}
+impl IntoLuau for &Function<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        (&self.value).push(state)
+    }
+}
+impl<'a> FromLuau<'a> for Function<'a> {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        expect_type(&value, lua_Type::LUA_TFUNCTION, "Function")?;
+
+        Ok(Function { value })
+    }
+}
+impl FromLuauArg for Function<'_> {
+    type Arg<'a> = Function<'a>;
+}
+
+impl Debug for Function<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ptr = unsafe { lua_topointer(self.value.state().as_ptr(), self.value.index()) };
+
+        write!(f, "<Luau Function {ptr:p}>")
+    }
+}
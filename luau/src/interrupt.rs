@@ -0,0 +1,181 @@
+//! Preempting running luau code
+//!
+//! Luau calls the interrupt callback at safepoints: function calls and returns, loop iterations and garbage
+//! collection steps. The callback can stop the running code there by raising an error, or suspend its thread.
+
+use crate::{
+    error::{panic_message, raise, Error},
+    state::LuauState,
+};
+use luau_sys::{
+    shim::{self, INTERRUPT_YIELD},
+    vm::{lua_State, lua_gettop},
+};
+use std::{
+    ffi::c_int,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+pub(crate) type InterruptFn = dyn FnMut(&LuauState, Option<c_int>) -> InterruptAction + Send;
+
+/// What to do with the running code after the interrupt callback returns
+#[derive(Debug)]
+pub enum InterruptAction {
+    Continue,
+    /// Suspend the running thread, as if it called `coroutine.yield()`
+    ///
+    /// Resuming continues where the thread stopped, and any values passed are ignored. Raises an error if the
+    /// running code is not in a thread that can yield.
+    Yield,
+    /// Raise the error in the running code
+    ///
+    /// The error can be caught by `pcall`, but limiters keep raising it at every following safepoint.
+    /// Ignored during garbage collection steps.
+    Error(Error),
+}
+
+impl LuauState {
+    /// Sets the interrupt callback, replacing the previous one
+    ///
+    /// The callback gets the state of the running thread and, if it's called during a garbage collection step,
+    /// the gc state. In that case the returned action is ignored, since the code can't be stopped in the middle
+    /// of a step, and the state can't be used: anything that would push to its stack fails with
+    /// [`Error::GcStep`]. A panic during a step is raised at the next safepoint instead.
+    pub fn set_interrupt<F>(&self, f: F)
+    where
+        F: FnMut(&LuauState, Option<c_int>) -> InterruptAction + Send + 'static,
+    {
+        *self.data().interrupt.borrow_mut() = Some(Box::new(f));
+
+        unsafe { shim::lua_setinterrupt(self.as_ptr(), Some(interrupt)) };
+    }
+    pub fn remove_interrupt(&self) {
+        unsafe { shim::lua_setinterrupt(self.as_ptr(), None) };
+
+        *self.data().interrupt.borrow_mut() = None;
+    }
+}
+
+unsafe extern "C" fn interrupt(l: *mut lua_State, gc: c_int) -> c_int {
+    // the values of the running function stay where they are
+    let state = unsafe { LuauState::borrowed_at(l, lua_gettop(l)) };
+    let data = state.data();
+
+    if gc >= 0 {
+        // the stack of the running function may not be touched and nothing may be allocated in the middle of
+        // a step, so the action is dropped and a panic has to wait for the next safepoint
+        data.gc_step.set(true);
+        let result = catch_unwind(AssertUnwindSafe(|| run_interrupt(&state, Some(gc))));
+        data.gc_step.set(false);
+
+        if let Err(payload) = result {
+            data.interrupt_panic
+                .borrow_mut()
+                .get_or_insert(panic_message(payload));
+        }
+
+        return 0;
+    }
+
+    if let Some(message) = data.interrupt_panic.borrow_mut().take() {
+        return unsafe { raise(l, Error::Panic(message)) };
+    }
+
+    let result = catch_unwind(AssertUnwindSafe(|| run_interrupt(&state, None)));
+
+    match result {
+        Ok(InterruptAction::Continue) => 0,
+        Ok(InterruptAction::Yield) => INTERRUPT_YIELD,
+        Ok(InterruptAction::Error(e)) => unsafe { raise(l, e) },
+        Err(payload) => unsafe { raise(l, Error::Panic(panic_message(payload))) },
+    }
+}
+
+fn run_interrupt(state: &LuauState, gc: Option<c_int>) -> InterruptAction {
+    // the callback itself may run luau code, which doesn't get interrupted
+    let Ok(mut callback) = state.data().interrupt.try_borrow_mut() else {
+        return InterruptAction::Continue;
+    };
+    let Some(callback) = callback.as_mut() else {
+        return InterruptAction::Continue;
+    };
+
+    callback(state, gc)
+}
+
+/// Interrupt callback that stops the code after `n` safepoints
+///
+/// Each safepoint is a call, a return or a loop iteration, so this bounds the amount of work done, unlike
+/// [`deadline`] it's deterministic. It doesn't count instructions, any amount of them can run between two
+/// safepoints. Raises [`Error::Interrupted`] once exhausted.
+pub fn safepoint_budget(
+    n: u64,
+) -> impl FnMut(&LuauState, Option<c_int>) -> InterruptAction + Send + 'static {
+    let mut remaining = n;
+
+    move |_, gc| {
+        if gc.is_some() {
+            return InterruptAction::Continue;
+        }
+
+        match remaining.checked_sub(1) {
+            Some(left) => {
+                remaining = left;
+                InterruptAction::Continue
+            }
+            None => InterruptAction::Error(Error::Interrupted("safepoint budget exhausted")),
+        }
+    }
+}
+
+/// Interrupt callback that stops the code once the deadline passes, raising [`Error::Interrupted`]
+pub fn deadline(
+    at: Instant,
+) -> impl FnMut(&LuauState, Option<c_int>) -> InterruptAction + Send + 'static {
+    move |_, gc| {
+        if gc.is_none() && Instant::now() >= at {
+            InterruptAction::Error(Error::Interrupted("deadline exceeded"))
+        } else {
+            InterruptAction::Continue
+        }
+    }
+}
+
+/// A flag to stop running code from another thread
+///
+/// Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+    /// Interrupt callback that stops the code once the token is cancelled, raising [`Error::Interrupted`]
+    pub fn interrupt(
+        &self,
+    ) -> impl FnMut(&LuauState, Option<c_int>) -> InterruptAction + Send + 'static {
+        let token = self.clone();
+
+        move |_, gc| {
+            if gc.is_none() && token.is_cancelled() {
+                InterruptAction::Error(Error::Interrupted("cancelled"))
+            } else {
+                InterruptAction::Continue
+            }
+        }
+    }
+}
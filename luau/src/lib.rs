@@ -1,3 +1,29 @@
 pub mod allocator;
+pub mod buffer;
+pub mod convert;
+pub mod error;
+mod ffi;
+pub mod function;
+pub mod interrupt;
+pub mod light;
+pub mod load;
+pub mod owned;
+pub mod sandbox;
+pub mod stack;
 pub mod state;
+pub mod stdlib;
+pub mod string;
 pub mod table;
+pub mod thread;
+pub mod userdata;
+pub mod value;
+pub mod vector;
+
+pub use convert::{
+    FromLuau, FromLuauArg, FromLuauArgs, FromLuauMulti, IntoLuau, IntoLuauMulti, Variadic,
+};
+pub use error::{Error, Result};
+pub use stdlib::StdLib;
+pub use userdata::UserData;
+pub use value::Value;
+pub use vector::Vector;
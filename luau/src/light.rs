@@ -0,0 +1,145 @@
+use crate::{
+    convert::{type_of, FromLuau, FromLuauArg, IntoLuau},
+    error::{check, Error, Result},
+    stack::StackRef,
+    state::LuauState,
+};
+use luau_sys::{
+    shim,
+    vm::{lua_Type, lua_lightuserdatatag, lua_pushlightuserdatatagged, lua_tolightuserdata},
+};
+use std::{
+    ffi::{c_int, c_void, CString},
+    fmt::Debug,
+    hash::Hash,
+    marker::PhantomData,
+    ptr,
+};
+
+/// Rust types that can be referenced from luau with [`LightRef`]
+pub trait LightUserData: 'static {
+    /// Name of the type, shown by `typeof` and `tostring` and used in error messages
+    const NAME: &'static str;
+}
+
+/// A non-owning reference to a rust object, passed to luau as tagged light userdata
+///
+/// Only a pointer-sized value is stored, so these don't allocate on the luau heap. Neither luau nor this crate
+/// ever dereference it, so it may just as well hold an index or an id. Converting back from luau checks the tag,
+/// so a reference to one type can't be mistaken for a reference to another.
+pub struct LightRef<T> {
+    ptr: *mut c_void,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> LightRef<T> {
+    pub fn from_ptr(ptr: *const T) -> Self {
+        Self {
+            ptr: ptr as *mut c_void,
+            _marker: PhantomData,
+        }
+    }
+    pub fn as_ptr(self) -> *const T {
+        self.ptr as *const T
+    }
+    pub fn from_index(index: usize) -> Self {
+        Self {
+            ptr: ptr::without_provenance_mut(index),
+            _marker: PhantomData,
+        }
+    }
+    pub fn index(self) -> usize {
+        self.ptr.addr()
+    }
+}
+
+impl LuauState {
+    /// Returns the light userdata tag of `T`, naming it on first use
+    pub(crate) fn light_tag<T: LightUserData>(&self) -> Result<c_int> {
+        let registry = &self.data().light;
+        if let Some(tag) = registry.borrow().tag::<T>() {
+            return Ok(tag);
+        }
+
+        let tag = registry
+            .borrow()
+            .next_tag()
+            .ok_or(Error::TooManyLightTypes)?;
+
+        let name = CString::new(T::NAME).map_err(|_| Error::ToLuau {
+            from: "&str with a null byte",
+            to: "light userdata name",
+        })?;
+
+        let l = self.as_ptr();
+        unsafe { check(l, shim::lua_setlightuserdataname(l, tag, name.as_ptr()))? };
+
+        registry.borrow_mut().insert::<T>(tag, T::NAME);
+
+        Ok(tag)
+    }
+}
+
+impl<T: LightUserData> IntoLuau for LightRef<T> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        let tag = state.light_tag::<T>()?;
+
+        unsafe { lua_pushlightuserdatatagged(state.as_ptr(), self.ptr, tag) };
+
+        Ok(())
+    }
+}
+impl<'a, T: LightUserData> FromLuau<'a> for LightRef<T> {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        let state = value.state();
+        let l = state.as_ptr();
+
+        if type_of(&value) != lua_Type::LUA_TLIGHTUSERDATA {
+            return Err(Error::FromLuau {
+                from: value.type_name(),
+                to: T::NAME,
+            });
+        }
+
+        let tag = unsafe { lua_lightuserdatatag(l, value.index()) };
+        if Some(tag) != state.data().light.borrow().tag::<T>() {
+            return Err(Error::FromLuau {
+                from: state.data().light.borrow().name(tag).unwrap_or("userdata"),
+                to: T::NAME,
+            });
+        }
+
+        Ok(Self {
+            ptr: unsafe { lua_tolightuserdata(l, value.index()) },
+            _marker: PhantomData,
+        })
+    }
+}
+impl<T: LightUserData> FromLuauArg for LightRef<T> {
+    type Arg<'a> = LightRef<T>;
+}
+
+// derives would require the same traits from `T`
+
+impl<T> Clone for LightRef<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for LightRef<T> {}
+impl<T> PartialEq for LightRef<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
+impl<T> Eq for LightRef<T> {}
+impl<T> Hash for LightRef<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.ptr.hash(state);
+    }
+}
+impl<T> Debug for LightRef<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<LightRef {:p}>", self.ptr)
+    }
+}
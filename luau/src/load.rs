@@ -0,0 +1,217 @@
+use crate::{
+    convert::FromLuauMulti,
+    error::{pop_message, Error, Result},
+    function::Function,
+    stack::StackRef,
+    state::LuauState,
+    table::Table,
+};
+use luau_compiler::{compile, Bytecode, BytecodeError, CompilerOptions};
+use luau_sys::{
+    common::bytecode::LuauBytecodeTag,
+    shim,
+    vm::{lua_Status, lua_gettop, lua_mainthread, lua_remove},
+};
+use std::{error, ffi::CString, fmt::Display};
+
+/// Errors that can happen when loading bytecode, see [`LuauState::load`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadError {
+    /// The bytecode buffer is too short to even contain the header
+    Truncated,
+    /// The bytecode was produced by an unsupported version of the compiler
+    Version { version: u8, min: u8, max: u8 },
+    /// The buffer contains a compile error instead of bytecode
+    Compile(String),
+    /// The VM rejected the bytecode, with its error message
+    Invalid(String),
+    /// The bytecode failed verification, see [`Bytecode::verify`]
+    Verify(BytecodeError),
+    /// The chunk name contains a null byte
+    ChunkName,
+    /// The environment table belongs to a different state
+    ForeignEnv,
+    /// The allocator refused to give more memory
+    Memory,
+    /// There is no more space on the luau stack
+    StackOverflow,
+    /// Loaded from an interrupt callback during a garbage collection step
+    GcStep,
+    /// The VM raised an error while loading, with its message
+    Runtime(String),
+}
+
+impl LoadError {
+    /// Keeps the cause of a failure to make space on the stack
+    fn stack(error: Error) -> Self {
+        match error {
+            Error::Memory => LoadError::Memory,
+            Error::StackOverflow => LoadError::StackOverflow,
+            Error::GcStep => LoadError::GcStep,
+            e => LoadError::Runtime(e.to_string()),
+        }
+    }
+}
+
+impl LuauState {
+    /// Loads bytecode produced by [`luau_compiler::compile`] as a function
+    ///
+    /// The function uses the given table as its environment, or the globals if `None`.
+    ///
+    /// The bytecode is verified with [`Bytecode::verify`] first, so it can come from untrusted sources.
+    pub fn load(
+        &self,
+        chunk_name: &str,
+        bytecode: &[u8],
+        env: Option<&Table>,
+    ) -> Result<Function, LoadError> {
+        check_header(bytecode)?;
+
+        Bytecode::parse(bytecode)
+            .and_then(|b| b.verify())
+            .map_err(LoadError::Verify)?;
+
+        unsafe { self.load_unchecked(chunk_name, bytecode, env) }
+    }
+    /// Loads bytecode as a function like [`LuauState::load`], without verifying it
+    ///
+    /// # Safety
+    ///
+    /// The VM assumes the bytecode to be well formed, only the header is checked here. Loading anything other
+    /// than the unmodified output of [`luau_compiler::compile`] or bytecode that passed [`Bytecode::verify`] can
+    /// make the VM access memory out of bounds.
+    pub unsafe fn load_unchecked(
+        &self,
+        chunk_name: &str,
+        bytecode: &[u8],
+        env: Option<&Table>,
+    ) -> Result<Function, LoadError> {
+        let l = self.as_ptr();
+
+        check_header(bytecode)?;
+
+        let chunk_name = CString::new(chunk_name).map_err(|_| LoadError::ChunkName)?;
+
+        // the copy of the environment and the function
+        let guard = self.stack_guard(2).map_err(LoadError::stack)?;
+
+        let env = match env {
+            Some(env) => {
+                // tables can be shared between the threads of a state, but not with other states
+                if unsafe { lua_mainthread(env.value.state().as_ptr()) != lua_mainthread(l) } {
+                    return Err(LoadError::ForeignEnv);
+                }
+
+                // the loader takes an index on this stack, the table may be on the stack of another thread
+                unsafe {
+                    env.value.push_copy(self);
+                    lua_gettop(l)
+                }
+            }
+            None => 0,
+        };
+
+        let mut result = 0;
+        let status = unsafe {
+            shim::luau_load(
+                l,
+                chunk_name.as_ptr(),
+                bytecode.as_ptr().cast(),
+                bytecode.len(),
+                env,
+                &mut result,
+            )
+        };
+
+        if status == lua_Status::LUA_ERRMEM {
+            unsafe { Error::pop(l, status) };
+            return Err(LoadError::Memory);
+        }
+        if status != lua_Status::LUA_OK {
+            return Err(LoadError::Runtime(unsafe { pop_message(l) }));
+        }
+        if result != 0 {
+            // the error message is on the stack
+            return Err(LoadError::Invalid(unsafe { pop_message(l) }));
+        }
+
+        if env != 0 {
+            unsafe { lua_remove(l, env) };
+        }
+        guard.commit();
+
+        Ok(Function {
+            value: unsafe { StackRef::adopt(self) },
+        })
+    }
+    /// Compiles the source code, loads it and runs it, returning whatever the chunk returned
+    ///
+    /// The options are validated with [`LuauState::validate_options`] first.
+    pub fn exec<'a, R: FromLuauMulti<'a>>(
+        &'a self,
+        source: &str,
+        options: &CompilerOptions,
+    ) -> Result<R> {
+        self.validate_options(options)?;
+
+        let bytecode = compile(source, options)?;
+        // straight from the compiler
+        let function = unsafe { self.load_unchecked("=exec", &bytecode, None)? };
+
+        function.call(())
+    }
+}
+
+/// Checks the version of the bytecode and whether it's a compile error
+fn check_header(bytecode: &[u8]) -> Result<(), LoadError> {
+    let version = *bytecode.first().ok_or(LoadError::Truncated)?;
+    if version == 0 {
+        // compile errors are encoded as a null byte followed by the message
+        let message = String::from_utf8_lossy(&bytecode[1..]);
+        return Err(LoadError::Compile(
+            message.trim_start_matches(':').to_owned(),
+        ));
+    }
+
+    let min = LuauBytecodeTag::LBC_VERSION_MIN.0 as u8;
+    let max = LuauBytecodeTag::LBC_VERSION_MAX.0 as u8;
+    if !(min..=max).contains(&version) {
+        return Err(LoadError::Version { version, min, max });
+    }
+    // type information version
+    if bytecode.len() < 2 {
+        return Err(LoadError::Truncated);
+    }
+
+    Ok(())
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Truncated => write!(f, "bytecode is truncated"),
+            LoadError::Version { version, min, max } => write!(
+                f,
+                "bytecode version mismatch (expected [{min}..{max}], got {version})"
+            ),
+            LoadError::Compile(msg) => write!(f, "bytecode contains a compile error: {msg}"),
+            LoadError::Invalid(msg) => write!(f, "invalid bytecode: {msg}"),
+            LoadError::Verify(e) => write!(f, "bytecode failed verification: {e}"),
+            LoadError::ChunkName => write!(f, "chunk name contains a null byte"),
+            LoadError::ForeignEnv => write!(f, "environment table belongs to a different state"),
+            LoadError::Memory => write!(f, "not enough memory to load bytecode"),
+            LoadError::StackOverflow => write!(f, "luau stack overflow while loading bytecode"),
+            LoadError::GcStep => write!(f, "cannot load bytecode during a garbage collection step"),
+            LoadError::Runtime(msg) => write!(f, "error while loading bytecode: {msg}"),
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LoadError::Verify(e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Handles that keep luau values alive independently of the stack
+//!
+//! The values are referenced from the registry, so the handles have no lifetime and can be stored anywhere.
+//! A state is needed again to use them.
+
+use crate::{
+    convert::{FromLuau, FromLuauMulti, IntoLuau, IntoLuauMulti},
+    error::{check, Error, Result},
+    function::Function,
+    stack::StackRef,
+    state::LuauState,
+    table::Table,
+};
+use luau_sys::{
+    shim,
+    vm::{lua_State, lua_rawgeti, lua_unref, LUA_REGISTRYINDEX},
+};
+use std::{
+    ffi::c_int,
+    fmt::Debug,
+    mem,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
+/// Releases the references of dropped handles
+///
+/// Handles can be dropped on any thread, even after the state is gone. The reference is released right away
+/// if the state is alive and the dropping thread is the last one that used it, as recorded whenever stack
+/// space is reserved. Otherwise it's queued until the state releases it.
+pub(crate) struct Refs {
+    /// main thread of the state, `None` once it's closed, locked while releasing a reference
+    state: Mutex<Option<MainThread>>,
+    /// id of the last thread that used the state, see [`thread_id`]
+    user: AtomicU64,
+    /// references dropped while the state couldn't be reached
+    queue: Mutex<Vec<c_int>>,
+}
+
+struct MainThread(*mut lua_State);
+
+/// Only used while holding the lock, by the thread that last used the state
+unsafe impl Send for MainThread {}
+
+impl Refs {
+    pub(crate) fn new(l: *mut lua_State) -> Self {
+        Self {
+            state: Mutex::new(Some(MainThread(l))),
+            user: AtomicU64::new(0),
+            queue: Mutex::new(Vec::new()),
+        }
+    }
+    /// Records that the current thread uses the state
+    pub(crate) fn enter(&self) {
+        let id = thread_id();
+
+        if self.user.load(Ordering::Acquire) != id {
+            // waits for the previous user to finish releasing references
+            let _state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            self.user.store(id, Ordering::Release);
+        }
+    }
+    /// Forgets the state, called right before it's closed
+    pub(crate) fn close(&self) {
+        *self.state.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        self.queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+    fn release(&self, id: c_int) {
+        // another thread is releasing a reference, and may be the one using the state
+        let Ok(state) = self.state.try_lock() else {
+            self.enqueue(id);
+            return;
+        };
+
+        match &*state {
+            Some(l) if self.user.load(Ordering::Acquire) == thread_id() => unsafe {
+                lua_unref(l.0, id)
+            },
+            Some(_) => self.enqueue(id),
+            // released with the state
+            None => {}
+        }
+    }
+    fn enqueue(&self, id: c_int) {
+        self.queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(id);
+    }
+}
+
+/// Identifies the current OS thread, never 0
+fn thread_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static ID: u64 = NEXT.fetch_add(1, Ordering::Relaxed);
+    }
+
+    ID.with(|id| *id)
+}
+
+/// An owned reference to any luau value
+pub struct OwnedRef {
+    id: c_int,
+    refs: Arc<Refs>,
+}
+
+/// An owned reference to a luau table, see [`Table::into_owned`]
+#[derive(Debug)]
+pub struct OwnedTable(OwnedRef);
+
+/// An owned reference to a luau function, see [`Function::into_owned`]
+#[derive(Debug)]
+pub struct OwnedFunction(OwnedRef);
+
+impl LuauState {
+    /// Keeps the value alive until the returned handle is dropped
+    pub fn create_ref<V: IntoLuau>(&self, value: V) -> Result<OwnedRef> {
+        let l = self.as_ptr();
+
+        self.release_refs();
+
+        self.reserve(1)?;
+        value.push(self)?;
+        let value = unsafe { StackRef::adopt(self) };
+
+        let mut id = 0;
+        unsafe { check(l, shim::lua_ref(l, value.index(), &mut id))? };
+
+        Ok(OwnedRef {
+            id,
+            refs: self.data().refs.clone(),
+        })
+    }
+    /// Releases the references of handles that were dropped while the state couldn't be reached
+    ///
+    /// Handles dropped on the thread that last used the state release their reference right away, the others
+    /// are released here. Happens on its own whenever a new reference is created.
+    pub fn release_refs(&self) {
+        let l = self.as_ptr();
+        self.data().refs.enter();
+
+        let ids = mem::take(
+            &mut *self
+                .data()
+                .refs
+                .queue
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+
+        for id in ids {
+            unsafe { lua_unref(l, id) };
+        }
+    }
+}
+
+impl OwnedRef {
+    /// Pushes the value on the stack of the state and converts it
+    ///
+    /// Fails with [`Error::ForeignRef`] if the state is not the one the reference was created with.
+    pub fn get<'a, V: FromLuau<'a>>(&self, state: &'a LuauState) -> Result<V> {
+        state.reserve(1)?;
+        self.push_ref(state)?;
+
+        V::from_luau(unsafe { StackRef::adopt(state) })
+    }
+    /// The caller must make sure there is space on the stack
+    fn push_ref(&self, state: &LuauState) -> Result<()> {
+        if !Arc::ptr_eq(&self.refs, &state.data().refs) {
+            return Err(Error::ForeignRef);
+        }
+
+        unsafe { lua_rawgeti(state.as_ptr(), LUA_REGISTRYINDEX, self.id) };
+
+        Ok(())
+    }
+}
+
+impl Drop for OwnedRef {
+    fn drop(&mut self) {
+        self.refs.release(self.id);
+    }
+}
+
+impl IntoLuau for &OwnedRef {
+    fn push(self, state: &LuauState) -> Result<()> {
+        self.push_ref(state)
+    }
+}
+
+impl Debug for OwnedRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Luau ref {}>", self.id)
+    }
+}
+
+impl Table<'_> {
+    /// Keeps the table alive until the returned handle is dropped
+    pub fn into_owned(self) -> Result<OwnedTable> {
+        self.value.state().create_ref(self).map(OwnedTable)
+    }
+}
+
+impl OwnedTable {
+    pub fn get<'a>(&self, state: &'a LuauState) -> Result<Table<'a>> {
+        self.0.get(state)
+    }
+}
+
+impl IntoLuau for &OwnedTable {
+    fn push(self, state: &LuauState) -> Result<()> {
+        (&self.0).push(state)
+    }
+}
+
+impl Function<'_> {
+    /// Keeps the function alive until the returned handle is dropped
+    pub fn into_owned(self) -> Result<OwnedFunction> {
+        self.value.state().create_ref(self).map(OwnedFunction)
+    }
+}
+
+impl OwnedFunction {
+    pub fn get<'a>(&self, state: &'a LuauState) -> Result<Function<'a>> {
+        self.0.get(state)
+    }
+    /// Calls the function, see [`Function::call`]
+    pub fn call<'a, A: IntoLuauMulti, R: FromLuauMulti<'a>>(
+        &self,
+        state: &'a LuauState,
+        args: A,
+    ) -> Result<R> {
+        self.get(state)?.call(args)
+    }
+}
+
+impl IntoLuau for &OwnedFunction {
+    fn push(self, state: &LuauState) -> Result<()> {
+        (&self.0).push(state)
+    }
+}
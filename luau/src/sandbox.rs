@@ -0,0 +1,116 @@
+//! Running untrusted code, see [`LuauState::sandbox`]
+
+use crate::{
+    error::{check, Error, Result},
+    stack::StackRef,
+    state::LuauState,
+    stdlib::StdLib,
+    thread::Thread,
+};
+use luau_compiler::CompilerOptions;
+use luau_sys::{
+    shim,
+    vm::{
+        lua_Type, lua_gettop, lua_iscfunction, lua_pushvalue, lua_replace, lua_setfenv, lua_xpush,
+        LUA_GLOBALSINDEX,
+    },
+};
+use std::ffi::CString;
+
+impl LuauState {
+    /// Freezes the environment, opening the [`StdLib::SAFE`] libraries first if none were opened
+    ///
+    /// To choose the libraries, open them with [`LuauState::open_libs`] before calling this. The globals,
+    /// the library tables and the string metatable are made readonly, so scripts can't affect each other
+    /// through them, and the globals are marked safe for the fast paths of builtin functions. Scripts that
+    /// need globals of their own should run in [`Thread::sandboxed`] threads.
+    pub fn sandbox(&self) -> Result<()> {
+        let l = self.as_ptr();
+
+        // luaL_sandbox iterates the globals with a key and a value on the stack
+        self.reserve(3)?;
+
+        if self.opened_libs().is_empty() {
+            self.open_libs(StdLib::SAFE)?;
+        }
+        unsafe { check(l, shim::luaL_sandbox(l))? };
+        self.data().sandboxed.set(true);
+
+        Ok(())
+    }
+    pub fn is_sandboxed(&self) -> bool {
+        self.data().sandboxed.get()
+    }
+    /// Checks that the compiler options agree with the environment of the state
+    ///
+    /// In a sandbox, globals that already exist are frozen, so they can't be declared mutable. Scripts can
+    /// still have mutable globals of their own in sandboxed threads.
+    pub fn validate_options(&self, options: &CompilerOptions) -> Result<()> {
+        if !self.is_sandboxed() {
+            return Ok(());
+        }
+
+        let l = self.as_ptr();
+
+        for name in options.mutable_globals() {
+            let _guard = self.stack_guard(1)?;
+            // names come from strings without null bytes
+            let name_c = CString::new(name).unwrap();
+
+            let mut ty = 0;
+            unsafe {
+                check(
+                    l,
+                    shim::lua_rawgetfield(l, LUA_GLOBALSINDEX, name_c.as_ptr(), &mut ty),
+                )?
+            };
+
+            if ty != lua_Type::LUA_TNIL.0 as _ {
+                return Err(Error::ReadonlyGlobal(name.to_owned()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Thread<'a> {
+    /// Gives the thread its own globals table, which falls back to the globals of the state for reading
+    ///
+    /// If the thread hasn't started yet, its function is switched to the new globals too. Functions loaded
+    /// later have to be given the environment explicitly.
+    pub fn sandboxed(mut self) -> Result<Self> {
+        let state = self.value.state();
+        let l = state.as_ptr();
+        let thread = self.as_thread_ptr();
+
+        let mut ok = 0;
+        unsafe { check(thread, shim::lua_checkstack(thread, 3, &mut ok))? };
+        if ok == 0 {
+            return Err(Error::StackOverflow);
+        }
+
+        unsafe { check(thread, shim::luaL_sandboxthread(thread))? };
+
+        let fresh =
+            unsafe { lua_gettop(thread) } == 1 && unsafe { lua_iscfunction(thread, 1) } == 0;
+        if self.function.is_none() || !fresh {
+            return Ok(self);
+        }
+
+        // functions share their environment between all threads, so the thread gets a copy
+        unsafe {
+            check(thread, shim::lua_clonefunction(thread, 1))?;
+            lua_pushvalue(thread, LUA_GLOBALSINDEX);
+            lua_setfenv(thread, -2);
+            lua_replace(thread, 1);
+        }
+
+        // run the copy again after a reset
+        state.reserve(1)?;
+        unsafe { lua_xpush(thread, l, 1) };
+        self.function = Some(unsafe { StackRef::adopt(state) });
+
+        Ok(self)
+    }
+}
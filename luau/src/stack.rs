@@ -0,0 +1,199 @@
+use crate::{
+    error::{check, Error, Result},
+    state::LuauState,
+};
+use luau_sys::{
+    shim,
+    vm::{lua_gettop, lua_pushnil, lua_pushvalue, lua_settop, lua_type, lua_typename, lua_xpush},
+};
+use std::{
+    ffi::{c_int, CStr},
+    fmt::Debug,
+};
+
+/// Keeps track of which stack slots are owned by handles
+///
+/// Handles can be dropped in any order, so dropping one only marks its slot as free.
+/// The stack is actually shrunk once all slots above it are free too.
+#[derive(Debug)]
+pub(crate) struct Slots {
+    /// stack index right below the first tracked slot
+    base: c_int,
+    /// whether each slot is still in use, starting from `base + 1`
+    used: Vec<bool>,
+}
+
+impl Slots {
+    pub(crate) fn new(base: c_int) -> Self {
+        Self {
+            base,
+            used: Vec::new(),
+        }
+    }
+    /// stack index of the topmost tracked slot
+    fn top(&self) -> c_int {
+        self.base + self.used.len() as c_int
+    }
+}
+
+/// A single luau value owned by a slot on the stack
+///
+/// This is what all the handles such as [`Table`](crate::table::Table) are built on. It's mostly useful
+/// for implementing [`FromLuau`](crate::convert::FromLuau) for your own types.
+pub struct StackRef<'a> {
+    state: &'a LuauState,
+    index: c_int,
+}
+
+impl<'a> StackRef<'a> {
+    /// Takes ownership of the value on top of the stack
+    pub(crate) unsafe fn adopt(state: &'a LuauState) -> Self {
+        let index = unsafe { lua_gettop(state.as_ptr()) };
+
+        let mut slots = state.slots.borrow_mut();
+        debug_assert_eq!(index, slots.top() + 1, "untracked values on the stack");
+        slots.used.push(true);
+
+        Self { state, index }
+    }
+    /// Takes ownership of the `n` values on top of the stack, in stack order
+    pub(crate) unsafe fn adopt_many(state: &'a LuauState, n: c_int) -> Vec<Self> {
+        let top = unsafe { lua_gettop(state.as_ptr()) };
+
+        let mut slots = state.slots.borrow_mut();
+        debug_assert_eq!(top - n, slots.top(), "untracked values on the stack");
+        slots.used.extend((0..n).map(|_| true));
+
+        (top - n + 1..=top)
+            .map(|index| Self { state, index })
+            .collect()
+    }
+    /// Pushes a nil and takes ownership of it, for filling in missing values
+    pub(crate) fn nil(state: &'a LuauState) -> Result<Self> {
+        state.reserve(1)?;
+        unsafe { lua_pushnil(state.as_ptr()) };
+
+        Ok(unsafe { Self::adopt(state) })
+    }
+    /// The state that this value lives in
+    pub fn state(&self) -> &'a LuauState {
+        self.state
+    }
+    /// Absolute index of the slot on the stack
+    pub fn index(&self) -> c_int {
+        self.index
+    }
+    /// Name of the luau type of the value
+    pub fn type_name(&self) -> &'static str {
+        let l = self.state.as_ptr();
+        let name = unsafe { CStr::from_ptr(lua_typename(l, lua_type(l, self.index))) };
+
+        // type names are all static and ascii
+        name.to_str().unwrap()
+    }
+    /// Pushes a copy of the value on top of the stack of `state`
+    ///
+    /// `state` must belong to the same VM, and the caller must make sure there is space on the stack
+    pub(crate) unsafe fn push_copy(&self, state: &LuauState) {
+        if self.state.as_ptr() == state.as_ptr() {
+            unsafe { lua_pushvalue(state.as_ptr(), self.index) };
+        } else {
+            unsafe { lua_xpush(self.state.as_ptr(), state.as_ptr(), self.index) };
+        }
+    }
+}
+
+impl Drop for StackRef<'_> {
+    fn drop(&mut self) {
+        let l = self.state.as_ptr();
+        let mut slots = self.state.slots.borrow_mut();
+
+        let i = (self.index - slots.base - 1) as usize;
+        slots.used[i] = false;
+
+        // shrink the stack, but only if there are no temporary values above the tracked slots
+        if unsafe { lua_gettop(l) } == slots.top() {
+            while slots.used.last() == Some(&false) {
+                slots.used.pop();
+            }
+            unsafe { lua_settop(l, slots.top()) };
+        }
+    }
+}
+
+impl Debug for StackRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Luau {} at {}>", self.type_name(), self.index)
+    }
+}
+
+/// Restores the top of the stack when dropped, unless committed
+///
+/// Values pushed after the guard was created are popped, even on early returns and panics, except for those
+/// owned by handles. So a failed operation can't leave values behind on the stack.
+pub struct StackGuard<'a> {
+    state: &'a LuauState,
+    top: c_int,
+    committed: bool,
+}
+
+impl<'a> StackGuard<'a> {
+    /// Stack index of the top when the guard was created
+    pub fn top(&self) -> c_int {
+        self.top
+    }
+    /// Leaves the values pushed since the guard was created on the stack
+    pub(crate) fn commit(mut self) {
+        self.committed = true;
+    }
+}
+
+impl Drop for StackGuard<'_> {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+
+        let l = self.state.as_ptr();
+        let keep = self.top.max(self.state.slots.borrow().top());
+
+        if unsafe { lua_gettop(l) } > keep {
+            unsafe { lua_settop(l, keep) };
+        }
+    }
+}
+
+impl LuauState {
+    /// Records the top of the stack to restore it later, making sure there is space for `n` more values
+    pub fn stack_guard(&self, n: c_int) -> Result<StackGuard<'_>> {
+        self.reserve(n)?;
+
+        Ok(StackGuard {
+            state: self,
+            top: unsafe { lua_gettop(self.as_ptr()) },
+            committed: false,
+        })
+    }
+    /// Makes sure there is space for `n` more values on the stack
+    ///
+    /// Also records the thread using the state for [`OwnedRef`](crate::owned::OwnedRef). Handles can't leave the
+    /// thread of the state, so a state moved to another thread makes room for a value here before anything
+    /// else that could race with releasing a reference.
+    pub(crate) fn reserve(&self, n: c_int) -> Result<()> {
+        let l = self.as_ptr();
+        self.data().refs.enter();
+
+        if self.data().gc_step.get() {
+            return Err(Error::GcStep);
+        }
+
+        let mut ok = 0;
+        unsafe { check(l, shim::lua_checkstack(l, n, &mut ok))? };
+
+        if ok == 0 {
+            return Err(Error::StackOverflow);
+        }
+
+        Ok(())
+    }
+}
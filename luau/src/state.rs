@@ -1,19 +1,124 @@
-use crate::allocator::{self, LuauAllocator, LuauAllocatorDefault};
-use luau_sys::vm::{lua_State, lua_close, lua_newstate};
-use std::{ffi::c_void, fmt::Debug, ptr::NonNull};
+use crate::{
+    allocator::{self, LuauAllocator, LuauAllocatorDefault},
+    error::{self, Error, Result, ERROR_TAG},
+    function,
+    interrupt::InterruptFn,
+    owned::Refs,
+    stack::Slots,
+    stdlib::StdLib,
+};
+use luau_sys::vm::{lua_State, lua_callbacks, lua_close, lua_newstate, LUA_LUTAG_LIMIT};
+use std::{
+    any::TypeId,
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    ffi::{c_int, c_void},
+    fmt::Debug,
+    ptr::NonNull,
+    sync::Arc,
+};
 
 pub struct LuauState {
     ptr: NonNull<lua_State>,
+    /// `None` for states borrowed inside of callbacks
+    owner: Option<Owner>,
+    pub(crate) slots: RefCell<Slots>,
+}
+
+/// Things that the main state owns and destroys when dropped
+struct Owner {
     allocator_ptr: *mut c_void,
     allocator_drop: unsafe fn(*mut c_void),
+    data: *mut StateData,
+}
+
+/// Rust side data shared by all threads of a VM, kept in the userdata field of the VM callbacks
+pub(crate) struct StateData {
+    pub(crate) userdata: RefCell<TagRegistry>,
+    pub(crate) light: RefCell<TagRegistry>,
+    pub(crate) interrupt: RefCell<Option<Box<InterruptFn>>>,
+    /// whether the interrupt callback is running during a garbage collection step
+    pub(crate) gc_step: Cell<bool>,
+    /// panic of the interrupt callback during a garbage collection step, raised at the next safepoint
+    pub(crate) interrupt_panic: RefCell<Option<String>>,
+    /// whether the globals were frozen by [`LuauState::sandbox`]
+    pub(crate) sandboxed: Cell<bool>,
+    pub(crate) libs: Cell<StdLib>,
+    pub(crate) refs: Arc<Refs>,
+    /// borrows of buffers by the address of their bytes, -1 for a mutable borrow
+    pub(crate) buffer_borrows: RefCell<HashMap<usize, isize>>,
+}
+
+impl StateData {
+    fn new(l: *mut lua_State) -> Self {
+        Self {
+            // the last userdata tag is used for errors
+            userdata: RefCell::new(TagRegistry::new(ERROR_TAG)),
+            light: RefCell::new(TagRegistry::new(LUA_LUTAG_LIMIT as c_int)),
+            interrupt: RefCell::new(None),
+            gc_step: Cell::new(false),
+            interrupt_panic: RefCell::new(None),
+            sandboxed: Cell::new(false),
+            libs: Cell::new(StdLib::NONE),
+            refs: Arc::new(Refs::new(l)),
+            buffer_borrows: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+/// Assigns a tag to each rust type, in the order they are first used
+///
+/// Tag 0 is left for values that don't belong to any rust type.
+pub(crate) struct TagRegistry {
+    tags: HashMap<TypeId, c_int>,
+    /// names of the registered types, the tag of each is its index + 1
+    names: Vec<&'static str>,
+    /// first tag that can't be used
+    limit: c_int,
+}
+
+impl TagRegistry {
+    fn new(limit: c_int) -> Self {
+        Self {
+            tags: HashMap::new(),
+            names: Vec::new(),
+            limit,
+        }
+    }
+    pub(crate) fn tag<T: 'static>(&self) -> Option<c_int> {
+        self.tags.get(&TypeId::of::<T>()).copied()
+    }
+    /// The tag that the next registered type will get, if there are any left
+    pub(crate) fn next_tag(&self) -> Option<c_int> {
+        let tag = self.names.len() as c_int + 1;
+
+        (tag < self.limit).then_some(tag)
+    }
+    pub(crate) fn insert<T: 'static>(&mut self, tag: c_int, name: &'static str) {
+        debug_assert_eq!(Some(tag), self.next_tag());
+
+        self.tags.insert(TypeId::of::<T>(), tag);
+        self.names.push(name);
+    }
+    pub(crate) fn name(&self, tag: c_int) -> Option<&'static str> {
+        let i = usize::try_from(tag).ok()?.checked_sub(1)?;
+
+        self.names.get(i).copied()
+    }
+    pub(crate) fn names(&self) -> &[&'static str] {
+        &self.names
+    }
 }
 
 impl LuauState {
-    pub fn new() -> Option<Self> {
+    pub fn new() -> Result<Self> {
         // Default allocator (using rust's allocator) with no limit
         Self::new_with_alloc(LuauAllocatorDefault::new(None))
     }
-    pub fn new_with_alloc<A: LuauAllocator>(alloc: A) -> Option<Self> {
+    /// Creates a state that allocates all of its memory with `alloc`
+    ///
+    /// Fails with [`Error::Memory`] if the allocator refuses to give memory for the state itself.
+    pub fn new_with_alloc<A: LuauAllocator>(alloc: A) -> Result<Self> {
         let alloc_raw_f = allocator::raw::<A>();
         let allocator_ptr = Box::into_raw(Box::new(alloc)) as *mut c_void;
 
@@ -23,21 +128,72 @@ impl LuauState {
             let _ = unsafe { Box::from_raw(ptr as *mut A) };
         }
 
-        NonNull::new(state_ptr).map(|ptr| Self {
+        let Some(ptr) = NonNull::new(state_ptr) else {
+            unsafe { allocator_drop::<A>(allocator_ptr) };
+            return Err(Error::Memory);
+        };
+
+        let data = Box::into_raw(Box::new(StateData::new(state_ptr)));
+        unsafe { (*lua_callbacks(state_ptr)).userdata = data as *mut c_void };
+
+        let state = Self {
             ptr,
-            allocator_ptr,
-            allocator_drop: allocator_drop::<A>,
-        })
+            owner: Some(Owner {
+                allocator_ptr,
+                allocator_drop: allocator_drop::<A>,
+                data,
+            }),
+            slots: RefCell::new(Slots::new(0)),
+        };
+
+        // the state is destroyed on drop if this fails
+        unsafe {
+            error::register_error_type(state.as_ptr())?;
+            function::register_traceback_handler(state.as_ptr())?;
+        }
+
+        Ok(state)
+    }
+    /// A non-owning state for the given thread, used inside of callbacks
+    ///
+    /// All values on the stack are left untracked
+    pub(crate) unsafe fn borrowed(ptr: *mut lua_State) -> Self {
+        unsafe { Self::borrowed_at(ptr, 0) }
+    }
+    /// Like [`LuauState::borrowed`], for callbacks that run on top of values they don't own
+    ///
+    /// The values up to the stack index `base` are left alone, handles are only created above them.
+    pub(crate) unsafe fn borrowed_at(ptr: *mut lua_State, base: c_int) -> Self {
+        Self {
+            ptr: unsafe { NonNull::new_unchecked(ptr) },
+            owner: None,
+            slots: RefCell::new(Slots::new(base)),
+        }
+    }
+    pub(crate) fn as_ptr(&self) -> *mut lua_State {
+        self.ptr.as_ptr()
+    }
+    pub(crate) fn data(&self) -> &StateData {
+        unsafe { &*((*lua_callbacks(self.as_ptr())).userdata as *const StateData) }
     }
 }
 impl Drop for LuauState {
     fn drop(&mut self) {
+        let Some(owner) = &self.owner else {
+            return;
+        };
+
         unsafe {
+            // handles dropped from now on have nothing to release
+            (*owner.data).refs.close();
+
             // destroy the state
             lua_close(self.ptr.as_ptr());
 
             // destroy the allocator
-            (self.allocator_drop)(self.allocator_ptr);
+            (owner.allocator_drop)(owner.allocator_ptr);
+
+            drop(Box::from_raw(owner.data));
         }
     }
 }
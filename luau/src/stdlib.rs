@@ -0,0 +1,151 @@
+use crate::{
+    error::{check, Result},
+    state::LuauState,
+};
+use luau_sys::{
+    shim,
+    vm::{
+        lua_State, luaopen_base, luaopen_bit32, luaopen_buffer, luaopen_coroutine, luaopen_debug,
+        luaopen_math, luaopen_os, luaopen_string, luaopen_table, luaopen_utf8, luaopen_vector,
+    },
+};
+use std::{
+    ffi::{c_int, CStr},
+    fmt::Debug,
+    ops::{BitAnd, BitOr, BitOrAssign, Not, Sub},
+    ptr::null,
+};
+
+/// A set of luau standard libraries, see [`LuauState::open_libs`]
+///
+/// Combine them with `|` and remove some with `-`, for example `StdLib::ALL - StdLib::DEBUG`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct StdLib(u32);
+
+/// In the order they are opened, with their global names
+const LIBS: [(StdLib, &CStr, unsafe extern "C" fn(*mut lua_State) -> c_int); 11] = [
+    // the base library sets globals directly instead of a table
+    (StdLib::BASE, c"", luaopen_base),
+    (StdLib::COROUTINE, c"coroutine", luaopen_coroutine),
+    (StdLib::TABLE, c"table", luaopen_table),
+    (StdLib::OS, c"os", luaopen_os),
+    (StdLib::STRING, c"string", luaopen_string),
+    (StdLib::MATH, c"math", luaopen_math),
+    (StdLib::DEBUG, c"debug", luaopen_debug),
+    (StdLib::UTF8, c"utf8", luaopen_utf8),
+    (StdLib::BIT32, c"bit32", luaopen_bit32),
+    (StdLib::BUFFER, c"buffer", luaopen_buffer),
+    (StdLib::VECTOR, c"vector", luaopen_vector),
+];
+
+impl StdLib {
+    pub const NONE: StdLib = StdLib(0);
+    /// `print`, `pcall`, `typeof`, `tostring` and the rest of the global functions
+    pub const BASE: StdLib = StdLib(1 << 0);
+    pub const COROUTINE: StdLib = StdLib(1 << 1);
+    pub const TABLE: StdLib = StdLib(1 << 2);
+    pub const OS: StdLib = StdLib(1 << 3);
+    pub const STRING: StdLib = StdLib(1 << 4);
+    pub const MATH: StdLib = StdLib(1 << 5);
+    pub const DEBUG: StdLib = StdLib(1 << 6);
+    pub const UTF8: StdLib = StdLib(1 << 7);
+    pub const BIT32: StdLib = StdLib(1 << 8);
+    pub const BUFFER: StdLib = StdLib(1 << 9);
+    pub const VECTOR: StdLib = StdLib(1 << 10);
+    /// All of the libraries, the same as `luaL_openlibs`
+    pub const ALL: StdLib = StdLib((1 << 11) - 1);
+    /// The libraries opened by [`LuauState::sandbox`] if none were opened, all but [`StdLib::DEBUG`], which
+    /// can inspect the locals and upvalues of any function, and [`StdLib::OS`]
+    pub const SAFE: StdLib = StdLib(StdLib::ALL.0 & !(StdLib::DEBUG.0 | StdLib::OS.0));
+
+    pub const fn contains(self, other: StdLib) -> bool {
+        self.0 & other.0 == other.0
+    }
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for StdLib {
+    type Output = StdLib;
+
+    fn bitor(self, rhs: StdLib) -> StdLib {
+        StdLib(self.0 | rhs.0)
+    }
+}
+impl BitOrAssign for StdLib {
+    fn bitor_assign(&mut self, rhs: StdLib) {
+        self.0 |= rhs.0;
+    }
+}
+impl BitAnd for StdLib {
+    type Output = StdLib;
+
+    fn bitand(self, rhs: StdLib) -> StdLib {
+        StdLib(self.0 & rhs.0)
+    }
+}
+impl Sub for StdLib {
+    type Output = StdLib;
+
+    fn sub(self, rhs: StdLib) -> StdLib {
+        StdLib(self.0 & !rhs.0)
+    }
+}
+impl Not for StdLib {
+    type Output = StdLib;
+
+    fn not(self) -> StdLib {
+        StdLib(!self.0 & StdLib::ALL.0)
+    }
+}
+
+impl Debug for StdLib {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = LIBS
+            .iter()
+            .filter(|(lib, _, _)| self.contains(*lib))
+            .map(|(lib, name, _)| match *lib {
+                StdLib::BASE => "base",
+                _ => name.to_str().unwrap(),
+            });
+
+        f.debug_set().entries(names).finish()
+    }
+}
+
+impl LuauState {
+    /// Opens the given standard libraries, setting their tables as globals
+    ///
+    /// A new state has none of them open. Opening a library again sets its functions in the table it already
+    /// has, so fields added to the table stay, and fails once the table was made readonly by
+    /// [`LuauState::sandbox`].
+    pub fn open_libs(&self, libs: StdLib) -> Result<()> {
+        let l = self.as_ptr();
+
+        // the function and its argument
+        let _guard = self.stack_guard(2)?;
+
+        for (lib, name, open) in LIBS {
+            if !libs.contains(lib) {
+                continue;
+            }
+
+            unsafe {
+                check(l, shim::lua_pushcclosurek(l, Some(open), null(), 0, None))?;
+                check(l, shim::lua_pushstring(l, name.as_ptr()))?;
+                // pops the function and the name
+                check(l, shim::lua_call(l, 1, 0))?;
+            }
+        }
+
+        let data = self.data();
+        data.libs.set(data.libs.get() | libs);
+
+        Ok(())
+    }
+    /// The standard libraries opened so far
+    pub fn opened_libs(&self) -> StdLib {
+        self.data().libs.get()
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{
+    convert::{expect_type, FromLuau, FromLuauArg, IntoLuau},
+    error::{check, Error, Result},
+    stack::StackRef,
+    state::LuauState,
+};
+use luau_sys::{
+    shim,
+    vm::{lua_Type, lua_tolstring},
+};
+use std::{borrow::Cow, fmt::Debug, slice, str};
+
+/// Handle to a luau string
+///
+/// Luau strings are arbitrary bytes and are not necessarily valid utf8
+pub struct LuauString<'a> {
+    pub(crate) value: StackRef<'a>,
+}
+
+impl LuauState {
+    /// Creates a new luau string from arbitrary bytes
+    pub fn create_string(&self, s: impl AsRef<[u8]>) -> Result<LuauString> {
+        let l = self.as_ptr();
+        let s = s.as_ref();
+
+        self.reserve(1)?;
+        unsafe { check(l, shim::lua_pushlstring(l, s.as_ptr().cast(), s.len()))? };
+
+        Ok(LuauString {
+            value: unsafe { StackRef::adopt(self) },
+        })
+    }
+}
+
+impl<'a> LuauString<'a> {
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            let mut len = 0;
+            let ptr = lua_tolstring(self.value.state().as_ptr(), self.value.index(), &mut len);
+
+            // the string can't be collected while the handle keeps it on the stack
+            slice::from_raw_parts(ptr as *const u8, len)
+        }
+    }
+    /// Returns the string if it's valid utf8
+    pub fn to_str(&self) -> Result<&str> {
+        str::from_utf8(self.as_bytes()).map_err(|_| Error::FromLuau {
+            from: "string",
+            to: "&str",
+        })
+    }
+    pub fn to_string_lossy(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.as_bytes())
+    }
+}
+
+impl IntoLuau for LuauString<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        self.value.push(state)
+    }
+}
+impl IntoLuau for &LuauString<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        (&self.value).push(state)
+    }
+}
+impl<'a> FromLuau<'a> for LuauString<'a> {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        expect_type(&value, lua_Type::LUA_TSTRING, "LuauString")?;
+
+        Ok(LuauString { value })
+    }
+}
+impl FromLuauArg for LuauString<'_> {
+    type Arg<'a> = LuauString<'a>;
+}
+
+impl PartialEq for LuauString<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Debug for LuauString<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.to_string_lossy(), f)
+    }
+}
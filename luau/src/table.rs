@@ -1,20 +1,304 @@
-use crate::state::LuauState;
-use std::{fmt::Debug, marker::PhantomData};
+use crate::{
+    convert::{expect_type, FromLuau, FromLuauArg, IntoLuau},
+    error::{check, Error, Result},
+    stack::StackRef,
+    state::LuauState,
+};
+use luau_sys::{
+    shim,
+    vm::{
+        lua_Type, lua_getmetatable, lua_getreadonly, lua_objlen, lua_pushnil, lua_pushvalue,
+        lua_rawequal, lua_rawget, lua_rawgeti, lua_rawiter, lua_setreadonly, lua_topointer,
+        LUA_GLOBALSINDEX,
+    },
+};
+use std::{ffi::c_int, fmt::Debug, marker::PhantomData};
 
-#[derive(PartialEq)]
+/// Handle to a luau table
 pub struct Table<'a> {
-    stack_pos: usize,
-    _phantom: PhantomData<&'a ()>,
+    pub(crate) value: StackRef<'a>,
 }
 
 impl LuauState {
-    pub fn create_table(&self, prealloc_arr: usize, prealloc_map: usize) -> Table {
-        todo!()
+    /// Creates a new table, preallocating space for `prealloc_arr` array elements
+    /// and `prealloc_map` key-value pairs
+    pub fn create_table(&self, prealloc_arr: usize, prealloc_map: usize) -> Result<Table> {
+        let l = self.as_ptr();
+
+        self.reserve(1)?;
+        unsafe {
+            check(
+                l,
+                shim::lua_createtable(
+                    l,
+                    prealloc_arr.try_into().unwrap_or(c_int::MAX),
+                    prealloc_map.try_into().unwrap_or(c_int::MAX),
+                ),
+            )?
+        };
+
+        Ok(Table {
+            value: unsafe { StackRef::adopt(self) },
+        })
+    }
+    /// The table of global variables
+    ///
+    /// Functions loaded without an explicit environment use it, so values set here are visible to scripts.
+    pub fn globals(&self) -> Result<Table> {
+        self.reserve(1)?;
+        unsafe { lua_pushvalue(self.as_ptr(), LUA_GLOBALSINDEX) };
+
+        Ok(Table {
+            value: unsafe { StackRef::adopt(self) },
+        })
+    }
+    /// Sets a global variable, same as `globals().set(name, value)`
+    pub fn set_global<V: IntoLuau>(&self, name: &str, value: V) -> Result<()> {
+        self.globals()?.set(name, value)
+    }
+    /// Gets a global variable, same as `globals().get(name)`
+    pub fn get_global<'a, V: FromLuau<'a>>(&'a self, name: &str) -> Result<V> {
+        self.globals()?.get(name)
+    }
+}
+
+impl<'a> Table<'a> {
+    fn state(&self) -> &'a LuauState {
+        self.value.state()
+    }
+    fn check_writable(&self) -> Result<()> {
+        if self.is_readonly() {
+            return Err(Error::Readonly);
+        }
+
+        Ok(())
+    }
+    /// Pushes the key and the value, popping the key again if pushing the value fails
+    fn push_pair(&self, key: impl IntoLuau, value: impl IntoLuau) -> Result<()> {
+        let state = self.state();
+
+        let guard = state.stack_guard(2)?;
+        key.push(state)?;
+        value.push(state)?;
+        guard.commit();
+
+        Ok(())
+    }
+    /// Gets `table[key]`, may call the `__index` metamethod
+    pub fn get<K: IntoLuau, V: FromLuau<'a>>(&self, key: K) -> Result<V> {
+        let state = self.state();
+        let l = state.as_ptr();
+
+        // metamethods may run luau code
+        state.check_buffers()?;
+
+        let guard = state.stack_guard(1)?;
+        key.push(state)?;
+
+        let mut _ty = 0;
+        unsafe { check(l, shim::lua_gettable(l, self.value.index(), &mut _ty))? };
+        guard.commit();
+
+        V::from_luau(unsafe { StackRef::adopt(state) })
+    }
+    /// Sets `table[key] = value`, may call the `__newindex` metamethod
+    pub fn set<K: IntoLuau, V: IntoLuau>(&self, key: K, value: V) -> Result<()> {
+        let state = self.state();
+        let l = state.as_ptr();
+
+        state.check_buffers()?;
+        let _guard = state.stack_guard(0)?;
+        self.push_pair(key, value)?;
+
+        unsafe { check(l, shim::lua_settable(l, self.value.index())) }
+    }
+    /// Gets `table[key]` without invoking metamethods
+    pub fn raw_get<K: IntoLuau, V: FromLuau<'a>>(&self, key: K) -> Result<V> {
+        let state = self.state();
+
+        let guard = state.stack_guard(1)?;
+        key.push(state)?;
+
+        unsafe { lua_rawget(state.as_ptr(), self.value.index()) };
+        guard.commit();
+
+        V::from_luau(unsafe { StackRef::adopt(state) })
+    }
+    /// Sets `table[key] = value` without invoking metamethods
+    pub fn raw_set<K: IntoLuau, V: IntoLuau>(&self, key: K, value: V) -> Result<()> {
+        let l = self.state().as_ptr();
+
+        // luau only checks this with assertions in the raw functions
+        self.check_writable()?;
+        self.push_pair(key, value)?;
+
+        unsafe { check(l, shim::lua_rawset(l, self.value.index())) }
+    }
+    /// Length of the array part, same as the `#` operator but without invoking metamethods
+    pub fn len(&self) -> usize {
+        unsafe { lua_objlen(self.state().as_ptr(), self.value.index()) as usize }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Appends a value to the end of the array part
+    pub fn push<V: IntoLuau>(&self, value: V) -> Result<()> {
+        let state = self.state();
+        let l = state.as_ptr();
+
+        self.check_writable()?;
+        let _guard = state.stack_guard(1)?;
+        value.push(state)?;
+
+        let n = (self.len() + 1) as c_int;
+        unsafe { check(l, shim::lua_rawseti(l, self.value.index(), n)) }
+    }
+    /// Removes the last value of the array part and returns it
+    ///
+    /// Returns nil if the array part is empty
+    pub fn pop<V: FromLuau<'a>>(&self) -> Result<V> {
+        let state = self.state();
+        let l = state.as_ptr();
+
+        self.check_writable()?;
+        let guard = state.stack_guard(2)?;
+
+        let n = self.len() as c_int;
+        if n == 0 {
+            unsafe { lua_pushnil(l) };
+        } else {
+            unsafe {
+                lua_rawgeti(l, self.value.index(), n);
+                lua_pushnil(l);
+                check(l, shim::lua_rawseti(l, self.value.index(), n))?;
+            }
+        }
+        guard.commit();
+
+        V::from_luau(unsafe { StackRef::adopt(state) })
+    }
+    /// Iterates over all key-value pairs of the table, without invoking metamethods
+    ///
+    /// The table must not be modified while iterating, except for assigning to existing keys
+    pub fn pairs<K: FromLuau<'a>, V: FromLuau<'a>>(&self) -> Pairs<'a, '_, K, V> {
+        Pairs {
+            table: self,
+            iter: 0,
+            _phantom: PhantomData,
+        }
+    }
+    /// Sets or removes the metatable, fails if the table is readonly
+    pub fn set_metatable(&self, metatable: Option<&Table>) -> Result<()> {
+        let state = self.state();
+        let l = state.as_ptr();
+
+        let _guard = state.stack_guard(1)?;
+        metatable.map(|t| &t.value).push(state)?;
+
+        let mut _r = 0;
+        unsafe { check(l, shim::lua_setmetatable(l, self.value.index(), &mut _r)) }
+    }
+    /// Returns the metatable, if there is one
+    pub fn metatable(&self) -> Result<Option<Table<'a>>> {
+        let state = self.state();
+
+        state.reserve(1)?;
+
+        if unsafe { lua_getmetatable(state.as_ptr(), self.value.index()) } == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Table {
+            value: unsafe { StackRef::adopt(state) },
+        }))
+    }
+    /// Makes the table readonly or writable again
+    pub fn set_readonly(&self, readonly: bool) {
+        unsafe { lua_setreadonly(self.state().as_ptr(), self.value.index(), readonly as c_int) };
+    }
+    pub fn is_readonly(&self) -> bool {
+        unsafe { lua_getreadonly(self.state().as_ptr(), self.value.index()) != 0 }
+    }
+    /// Removes all entries while keeping the allocated space
+    pub fn clear(&self) -> Result<()> {
+        let l = self.state().as_ptr();
+
+        self.check_writable()?;
+
+        unsafe { check(l, shim::lua_cleartable(l, self.value.index())) }
+    }
+}
+
+/// Iterator over the key-value pairs of a table, see [`Table::pairs`]
+pub struct Pairs<'a, 't, K, V> {
+    table: &'t Table<'a>,
+    iter: c_int,
+    _phantom: PhantomData<(K, V)>,
+}
+
+impl<'a, K: FromLuau<'a>, V: FromLuau<'a>> Iterator for Pairs<'a, '_, K, V> {
+    type Item = Result<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.iter < 0 {
+            return None;
+        }
+
+        let state = self.table.state();
+
+        if let Err(e) = state.reserve(2) {
+            self.iter = -1;
+            return Some(Err(e));
+        }
+
+        // pushes the key and the value
+        self.iter = unsafe { lua_rawiter(state.as_ptr(), self.table.value.index(), self.iter) };
+        if self.iter < 0 {
+            return None;
+        }
+
+        let mut pair = unsafe { StackRef::adopt_many(state, 2) };
+        let value = pair.pop().unwrap();
+        let key = pair.pop().unwrap();
+
+        Some(K::from_luau(key).and_then(|k| Ok((k, V::from_luau(value)?))))
+    }
+}
+
+impl IntoLuau for Table<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        self.value.push(state)
+    }
+}
+impl IntoLuau for &Table<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        (&self.value).push(state)
+    }
+}
+impl<'a> FromLuau<'a> for Table<'a> {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        expect_type(&value, lua_Type::LUA_TTABLE, "Table")?;
+
+        Ok(Table { value })
+    }
+}
+impl FromLuauArg for Table<'_> {
+    type Arg<'a> = Table<'a>;
+}
+
+impl PartialEq for Table<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = (&self.value, &other.value);
+
+        a.state().as_ptr() == b.state().as_ptr()
+            && unsafe { lua_rawequal(a.state().as_ptr(), a.index(), b.index()) != 0 }
     }
 }
 
 impl<'a> Debug for Table<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "<Luau Table {:x}>", self.stack_pos)
+        let ptr = unsafe { lua_topointer(self.state().as_ptr(), self.value.index()) };
+
+        write!(f, "<Luau Table {ptr:p}>")
     }
 }
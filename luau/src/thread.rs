@@ -0,0 +1,384 @@
+use crate::{
+    convert::{
+        expect_type, type_of, FromLuau, FromLuauArg, FromLuauMulti, IntoLuau, IntoLuauMulti,
+    },
+    error::{check, push_error, Error, Result},
+    function::{traceback, AsyncCall, Function, PendingCall},
+    stack::StackRef,
+    state::LuauState,
+    userdata::AnyUserData,
+};
+use luau_sys::{
+    shim,
+    vm::{
+        lua_CoStatus, lua_State, lua_Status, lua_Type, lua_costatus, lua_gettop, lua_resetthread,
+        lua_resume, lua_resumeerror, lua_settop, lua_topointer, lua_tothread, lua_xmove, lua_xpush,
+    },
+};
+use std::{
+    ffi::c_int,
+    fmt::Debug,
+    marker::PhantomData,
+    mem,
+    pin::Pin,
+    ptr::null_mut,
+    task::{Context, Poll},
+};
+
+/// Handle to a luau thread (coroutine)
+pub struct Thread<'a> {
+    pub(crate) value: StackRef<'a>,
+    /// the function that the thread was created with, to run again after a reset
+    pub(crate) function: Option<StackRef<'a>>,
+}
+
+/// Outcome of resuming a [`Thread`]
+#[derive(Debug)]
+pub enum ThreadResult<R> {
+    /// The thread yielded these values and can be resumed again
+    Yielded(R),
+    /// The function returned these values, the thread is finished
+    Finished(R),
+    /// An error was raised, the thread can't be resumed anymore
+    Error(Error),
+}
+
+/// Status of a [`Thread`], as returned by `coroutine.status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadStatus {
+    /// The thread is running, it's the one calling this
+    Running,
+    /// The thread is yielded or hasn't been started yet
+    Suspended,
+    /// The thread resumed another thread and is waiting for it
+    Normal,
+    /// The function returned
+    Finished,
+    /// The thread stopped with an error
+    Error,
+}
+
+/// Values to yield from a rust function instead of returning them
+///
+/// Only works when the function is called from a thread that is being resumed, otherwise an error is raised.
+/// The values passed to the next resume are returned from the function call in luau.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Yield<T>(pub T);
+
+impl<T: IntoLuauMulti> IntoLuauMulti for Yield<T> {
+    fn push_multi(self, state: &LuauState) -> Result<c_int> {
+        self.0.push_multi(state)
+    }
+    fn yields(&self) -> bool {
+        true
+    }
+}
+
+impl LuauState {
+    /// Creates a new thread that runs the function when first resumed
+    pub fn create_thread<'a>(&'a self, function: &Function) -> Result<Thread<'a>> {
+        let l = self.as_ptr();
+
+        // the thread and the kept function
+        self.reserve(2)?;
+
+        let mut thread = null_mut();
+        unsafe { check(l, shim::lua_newthread(l, &mut thread))? };
+        let value = unsafe { StackRef::adopt(self) };
+
+        // new threads always have some stack space
+        unsafe {
+            lua_xpush(
+                function.value.state().as_ptr(),
+                thread,
+                function.value.index(),
+            )
+        };
+
+        unsafe { function.value.push_copy(self) };
+        let function = unsafe { StackRef::adopt(self) };
+
+        Ok(Thread {
+            value,
+            function: Some(function),
+        })
+    }
+}
+
+impl<'a> Thread<'a> {
+    /// Starts or continues running the thread, passing the arguments to the function or returning them from
+    /// the yield that suspended it
+    pub fn resume<A: IntoLuauMulti, R: FromLuauMulti<'a>>(&self, args: A) -> ThreadResult<R> {
+        match self.resume_inner(args) {
+            Ok(result) => result,
+            Err(e) => ThreadResult::Error(e),
+        }
+    }
+    fn resume_inner<A: IntoLuauMulti, R: FromLuauMulti<'a>>(
+        &self,
+        args: A,
+    ) -> Result<ThreadResult<R>> {
+        let state = self.value.state();
+
+        let nargs = self.push_args(args)?;
+        let (finished, values) = unsafe { self.resume_raw(nargs, false)? };
+        let values = R::from_luau_multi(state, values)?;
+
+        Ok(if finished {
+            ThreadResult::Finished(values)
+        } else {
+            ThreadResult::Yielded(values)
+        })
+    }
+    /// Moves the values on top of the stack of the thread, returning how many there are
+    pub(crate) fn push_args<A: IntoLuauMulti>(&self, args: A) -> Result<c_int> {
+        let state = self.value.state();
+        let l = state.as_ptr();
+        let thread = self.as_thread_ptr();
+
+        let _guard = state.stack_guard(0)?;
+        let nargs = args.push_multi(state)?;
+
+        let mut ok = 0;
+        unsafe { check(thread, shim::lua_checkstack(thread, nargs, &mut ok))? };
+        if ok == 0 {
+            return Err(Error::StackOverflow);
+        }
+
+        unsafe { lua_xmove(l, thread, nargs) };
+
+        Ok(nargs)
+    }
+    /// Resumes the thread with the `nargs` values on top of its stack, returning whether it finished and the
+    /// values it returned or yielded
+    ///
+    /// If `error` is set, the value on top of the stack is raised as an error where the thread yielded instead.
+    pub(crate) unsafe fn resume_raw(
+        &self,
+        nargs: c_int,
+        error: bool,
+    ) -> Result<(bool, Vec<StackRef<'a>>)> {
+        let state = self.value.state();
+        let l = state.as_ptr();
+        let thread = self.as_thread_ptr();
+
+        state.check_buffers()?;
+
+        let status = lua_Status(unsafe {
+            if error {
+                lua_resumeerror(thread, l)
+            } else {
+                lua_resume(thread, l, nargs)
+            }
+        } as _);
+        match status {
+            lua_Status::LUA_OK | lua_Status::LUA_YIELD | lua_Status::LUA_BREAK => {
+                let nresults = unsafe { lua_gettop(thread) };
+                if let Err(e) = state.reserve(nresults) {
+                    unsafe { lua_settop(thread, 0) };
+                    return Err(e);
+                }
+
+                unsafe { lua_xmove(thread, l, nresults) };
+                let values = unsafe { StackRef::adopt_many(state, nresults) };
+
+                Ok((status == lua_Status::LUA_OK, values))
+            }
+            _ => {
+                // the call stack of a thread is left as it was when the error was raised
+                let traceback = unsafe { traceback(thread, 0) };
+
+                Err(match unsafe { Error::pop(thread, status) } {
+                    Error::Runtime { message, .. } => Error::Runtime {
+                        message,
+                        traceback: Some(traceback),
+                    },
+                    e => e,
+                })
+            }
+        }
+    }
+    pub fn status(&self) -> ThreadStatus {
+        let status = unsafe { lua_costatus(self.value.state().as_ptr(), self.as_thread_ptr()) };
+
+        match lua_CoStatus(status as _) {
+            lua_CoStatus::LUA_CORUN => ThreadStatus::Running,
+            lua_CoStatus::LUA_COSUS => ThreadStatus::Suspended,
+            lua_CoStatus::LUA_CONOR => ThreadStatus::Normal,
+            lua_CoStatus::LUA_COFIN => ThreadStatus::Finished,
+            _ => ThreadStatus::Error,
+        }
+    }
+    /// Whether the thread can be resumed
+    pub fn is_resumable(&self) -> bool {
+        self.status() == ThreadStatus::Suspended
+    }
+    /// Discards whatever the thread was doing, so that it runs its function again from the start when resumed
+    ///
+    /// Threads that were not created with [`LuauState::create_thread`] don't have a known function, so they are
+    /// left empty. Running threads can't be reset.
+    pub fn reset(&self) -> Result<()> {
+        if matches!(self.status(), ThreadStatus::Running | ThreadStatus::Normal) {
+            return Err(Error::runtime("cannot reset a running thread".to_owned()));
+        }
+
+        let thread = self.as_thread_ptr();
+        unsafe {
+            lua_resetthread(thread);
+
+            if let Some(function) = &self.function {
+                lua_xpush(function.state().as_ptr(), thread, function.index());
+            }
+        }
+
+        Ok(())
+    }
+    pub(crate) fn as_thread_ptr(&self) -> *mut lua_State {
+        unsafe { lua_tothread(self.value.state().as_ptr(), self.value.index()) }
+    }
+}
+
+impl<'a> Thread<'a> {
+    /// Returns a future that runs the thread to completion, passing it the arguments when starting
+    ///
+    /// Polling it resumes the thread, and polls the futures of the async rust functions it calls (see
+    /// [`LuauState::create_async_function`]). Any other yields are treated like a rust future yielding
+    /// control, the thread is resumed without values on the next poll. Works with any executor.
+    pub fn into_future<A: IntoLuauMulti, R: FromLuauMulti<'a>>(
+        self,
+        args: A,
+    ) -> ThreadFuture<'a, R> {
+        let step = match self.push_args(args) {
+            Ok(nargs) => Step::Resume(nargs),
+            Err(e) => Step::Failed(e),
+        };
+
+        ThreadFuture {
+            thread: self,
+            step,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Future that runs a [`Thread`] to completion, returned by [`Thread::into_future`]
+pub struct ThreadFuture<'a, R> {
+    thread: Thread<'a>,
+    step: Step,
+    _marker: PhantomData<fn() -> R>,
+}
+
+enum Step {
+    /// Resume with this many values on top of the stack of the thread
+    Resume(c_int),
+    /// Raise the value on top of the stack of the thread where it yielded
+    Raise,
+    /// Waiting for an async rust function to complete
+    Wait(AsyncCall),
+    Failed(Error),
+    Done,
+}
+
+impl<'a, R: FromLuauMulti<'a>> Future for ThreadFuture<'a, R> {
+    type Output = Result<R>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let state = this.thread.value.state();
+        let thread = this.thread.as_thread_ptr();
+
+        loop {
+            let (nargs, error) = match mem::replace(&mut this.step, Step::Done) {
+                Step::Resume(nargs) => (nargs, false),
+                Step::Raise => (0, true),
+                Step::Wait(mut call) => {
+                    this.step = match call.as_mut().poll(cx) {
+                        Poll::Pending => {
+                            this.step = Step::Wait(call);
+                            return Poll::Pending;
+                        }
+                        Poll::Ready(results) => {
+                            match results.and_then(|r| this.thread.push_args(r)) {
+                                Ok(nargs) => Step::Resume(nargs),
+                                Err(e) => {
+                                    // the yielding rust function left space for at least its own results
+                                    unsafe { push_error(thread, e) };
+                                    Step::Raise
+                                }
+                            }
+                        }
+                    };
+                    continue;
+                }
+                Step::Failed(e) => return Poll::Ready(Err(e)),
+                Step::Done => panic!("ThreadFuture polled after completion"),
+            };
+
+            let (finished, values) = match unsafe { this.thread.resume_raw(nargs, error) } {
+                Ok(result) => result,
+                Err(e) => return Poll::Ready(Err(e)),
+            };
+            if finished {
+                return Poll::Ready(R::from_luau_multi(state, values));
+            }
+
+            match take_call(values) {
+                Some(call) => this.step = Step::Wait(call),
+                None => {
+                    this.step = Step::Resume(0);
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+/// Takes the future out of the values yielded by an async rust function
+fn take_call(values: Vec<StackRef>) -> Option<AsyncCall> {
+    let [value] = <[_; 1]>::try_from(values).ok()?;
+    if type_of(&value) != lua_Type::LUA_TUSERDATA {
+        return None;
+    }
+
+    let userdata = AnyUserData { value };
+    userdata.borrow_mut::<PendingCall>().ok()?.0.take()
+}
+
+impl IntoLuau for Thread<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        self.value.push(state)
+    }
+}
+impl IntoLuau for &Thread<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        (&self.value).push(state)
+    }
+}
+impl<'a> FromLuau<'a> for Thread<'a> {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        expect_type(&value, lua_Type::LUA_TTHREAD, "Thread")?;
+
+        Ok(Thread {
+            value,
+            function: None,
+        })
+    }
+}
+impl FromLuauArg for Thread<'_> {
+    type Arg<'a> = Thread<'a>;
+}
+
+impl PartialEq for Thread<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_thread_ptr() == other.as_thread_ptr()
+    }
+}
+
+impl Debug for Thread<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ptr = unsafe { lua_topointer(self.value.state().as_ptr(), self.value.index()) };
+
+        write!(f, "<Luau Thread {ptr:p}>")
+    }
+}
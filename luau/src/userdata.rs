@@ -0,0 +1,642 @@
+use crate::{
+    convert::{
+        expect_type, FromLuau, FromLuauArg, FromLuauArgs, FromLuauMulti, IntoLuau, IntoLuauMulti,
+    },
+    error::{check, Error, Result},
+    ffi::{lua_pop, lua_upvalueindex},
+    function::push_results,
+    stack::StackRef,
+    state::LuauState,
+};
+use luau_sys::{
+    shim,
+    vm::{
+        lua_State, lua_Type, lua_gettop, lua_insert, lua_pushvalue, lua_rawequal, lua_rawget,
+        lua_setuserdatadtor, lua_setuserdatametatable, lua_tolstring, lua_topointer,
+        lua_touserdatatagged, lua_type, lua_userdatatag,
+    },
+};
+use std::{
+    cell::{Ref, RefCell, RefMut},
+    collections::HashMap,
+    error,
+    ffi::{c_int, c_void, CStr},
+    fmt::Debug,
+    marker::PhantomData,
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr::{self, null_mut},
+    slice, str,
+};
+
+/// Rust types that can be moved into luau as userdata
+///
+/// Values are stored inside the luau userdata itself and dropped when it's garbage collected.
+/// Borrows are checked at runtime, so for example a method can't mutate a value that is already
+/// borrowed further up the call stack, an [`Error::UserDataBorrowMut`] is returned instead.
+pub trait UserData: Send + 'static {
+    /// Name of the type, returned by `typeof` and used in error messages
+    const NAME: &'static str;
+
+    /// Declares the fields, methods and metamethods of the type
+    ///
+    /// Called once per state, when the first value of this type is pushed
+    fn register(methods: &mut UserDataMethods<Self>)
+    where
+        Self: Sized,
+    {
+        let _ = methods;
+    }
+}
+
+/// Metamethods that can be implemented for userdata
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetaMethod {
+    /// `__index`, called for keys that are neither fields nor methods
+    Index,
+    /// `__newindex`, called for keys that don't have a field setter
+    NewIndex,
+    /// `__call`
+    Call,
+    /// `__tostring`
+    ToString,
+    /// `__len`, the `#` operator
+    Len,
+    /// `__eq`, only called when comparing with another userdata
+    Eq,
+    /// `__lt`
+    Lt,
+    /// `__le`
+    Le,
+    /// `__add`
+    Add,
+    /// `__sub`
+    Sub,
+    /// `__mul`
+    Mul,
+    /// `__div`
+    Div,
+    /// `__idiv`, the `//` operator
+    IDiv,
+    /// `__mod`
+    Mod,
+    /// `__pow`
+    Pow,
+    /// `__unm`, unary minus
+    Unm,
+    /// `__concat`, the `..` operator
+    Concat,
+    /// `__iter`, generalized iteration in `for` loops
+    Iter,
+}
+
+impl MetaMethod {
+    /// Name of the metatable field
+    pub fn name(self) -> &'static CStr {
+        match self {
+            MetaMethod::Index => c"__index",
+            MetaMethod::NewIndex => c"__newindex",
+            MetaMethod::Call => c"__call",
+            MetaMethod::ToString => c"__tostring",
+            MetaMethod::Len => c"__len",
+            MetaMethod::Eq => c"__eq",
+            MetaMethod::Lt => c"__lt",
+            MetaMethod::Le => c"__le",
+            MetaMethod::Add => c"__add",
+            MetaMethod::Sub => c"__sub",
+            MetaMethod::Mul => c"__mul",
+            MetaMethod::Div => c"__div",
+            MetaMethod::IDiv => c"__idiv",
+            MetaMethod::Mod => c"__mod",
+            MetaMethod::Pow => c"__pow",
+            MetaMethod::Unm => c"__unm",
+            MetaMethod::Concat => c"__concat",
+            MetaMethod::Iter => c"__iter",
+        }
+    }
+}
+
+/// Works with the stack directly, see [`LuauState::push_raw_function`]
+type Callback = Box<dyn Fn(&LuauState) -> Result<c_int> + Send>;
+
+/// Collects the fields, methods and metamethods of a [`UserData`] type
+///
+/// In all of the closures, the first argument is the state of the calling thread, followed by the value itself.
+/// Declaring the same name twice replaces the previous declaration.
+pub struct UserDataMethods<T> {
+    getters: HashMap<String, Callback>,
+    setters: HashMap<String, Callback>,
+    methods: HashMap<String, Callback>,
+    meta_methods: HashMap<MetaMethod, Callback>,
+    _marker: PhantomData<fn(&mut T)>,
+}
+
+impl<T: UserData> UserDataMethods<T> {
+    fn new() -> Self {
+        Self {
+            getters: HashMap::new(),
+            setters: HashMap::new(),
+            methods: HashMap::new(),
+            meta_methods: HashMap::new(),
+            _marker: PhantomData,
+        }
+    }
+    /// Adds a field that can be read with `value.name`
+    pub fn add_field_getter<R, E, F>(&mut self, name: &str, get: F)
+    where
+        R: IntoLuau,
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+        F: Fn(&LuauState, &T) -> Result<R, E> + Send + 'static,
+    {
+        let callback = move |state: &LuauState| {
+            let (this, _) = this_and_args::<T>(state)?;
+            let value =
+                get(state, &*this.borrow::<T>()?).map_err(|e| Error::from_callback(e.into()))?;
+
+            state.reserve(1)?;
+            value.push(state)?;
+
+            Ok(1)
+        };
+
+        self.getters.insert(name.to_owned(), Box::new(callback));
+    }
+    /// Adds a field that can be assigned with `value.name = x`
+    pub fn add_field_setter<A, E, F>(&mut self, name: &str, set: F)
+    where
+        A: FromLuauArg,
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+        F: Fn(&LuauState, &mut T, A) -> Result<(), E>
+            + for<'a> Fn(&'a LuauState, &mut T, A::Arg<'a>) -> Result<(), E>
+            + Send
+            + 'static,
+    {
+        let callback = move |state: &LuauState| {
+            // the arguments are the userdata, the key and the new value
+            let (this, mut args) = this_and_args::<T>(state)?;
+            let value = args.pop().map_or_else(|| StackRef::nil(state), Ok)?;
+            let value = <A::Arg<'_> as FromLuau>::from_luau(value)?;
+
+            call_method(&set, state, &mut *this.borrow_mut::<T>()?, value)
+                .map_err(|e| Error::from_callback(e.into()))?;
+
+            Ok(0)
+        };
+
+        self.setters.insert(name.to_owned(), Box::new(callback));
+    }
+    /// Adds a method, called as `value:name(...)`
+    pub fn add_method<A, R, E, F>(&mut self, name: &str, method: F)
+    where
+        A: FromLuauArgs,
+        R: IntoLuauMulti,
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+        F: Fn(&LuauState, &T, A) -> Result<R, E>
+            + for<'a> Fn(&'a LuauState, &T, A::Args<'a>) -> Result<R, E>
+            + Send
+            + 'static,
+    {
+        self.methods
+            .insert(name.to_owned(), Box::new(method_callback(method)));
+    }
+    /// Adds a method that can mutate the value, called as `value:name(...)`
+    pub fn add_method_mut<A, R, E, F>(&mut self, name: &str, method: F)
+    where
+        A: FromLuauArgs,
+        R: IntoLuauMulti,
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+        F: Fn(&LuauState, &mut T, A) -> Result<R, E>
+            + for<'a> Fn(&'a LuauState, &mut T, A::Args<'a>) -> Result<R, E>
+            + Send
+            + 'static,
+    {
+        self.methods
+            .insert(name.to_owned(), Box::new(method_mut_callback(method)));
+    }
+    /// Adds a metamethod, the value is always the first operand
+    ///
+    /// [`MetaMethod::Index`] and [`MetaMethod::NewIndex`] are only called for keys that don't match any fields or methods.
+    pub fn add_meta_method<A, R, E, F>(&mut self, meta: MetaMethod, method: F)
+    where
+        A: FromLuauArgs,
+        R: IntoLuauMulti,
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+        F: Fn(&LuauState, &T, A) -> Result<R, E>
+            + for<'a> Fn(&'a LuauState, &T, A::Args<'a>) -> Result<R, E>
+            + Send
+            + 'static,
+    {
+        self.meta_methods
+            .insert(meta, Box::new(method_callback(method)));
+    }
+    /// Adds a metamethod that can mutate the value, see [`UserDataMethods::add_meta_method`]
+    pub fn add_meta_method_mut<A, R, E, F>(&mut self, meta: MetaMethod, method: F)
+    where
+        A: FromLuauArgs,
+        R: IntoLuauMulti,
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+        F: Fn(&LuauState, &mut T, A) -> Result<R, E>
+            + for<'a> Fn(&'a LuauState, &mut T, A::Args<'a>) -> Result<R, E>
+            + Send
+            + 'static,
+    {
+        self.meta_methods
+            .insert(meta, Box::new(method_mut_callback(method)));
+    }
+    /// Pushes the metatable built from the declarations
+    fn push_metatable(mut self, state: &LuauState) -> Result<()> {
+        let l = state.as_ptr();
+
+        // the metatable, the methods table, a function and its name
+        let guard = state.stack_guard(4)?;
+
+        unsafe {
+            check(
+                l,
+                shim::lua_createtable(l, 0, self.meta_methods.len() as c_int + 4),
+            )?;
+
+            // for `typeof`
+            check(
+                l,
+                shim::lua_pushlstring(l, T::NAME.as_ptr().cast(), T::NAME.len()),
+            )?;
+            check(l, shim::lua_rawsetfield(l, -2, c"__type".as_ptr()))?;
+
+            // scripts can't get or replace the metatable
+            check(
+                l,
+                shim::lua_pushstring(l, c"The metatable is locked".as_ptr()),
+            )?;
+            check(l, shim::lua_rawsetfield(l, -2, c"__metatable".as_ptr()))?;
+
+            check(l, shim::lua_createtable(l, 0, self.methods.len() as c_int))?;
+            for (name, method) in self.methods {
+                state.push_raw_function(method, 0)?;
+                raw_set_name(l, &name)?;
+            }
+
+            // the methods table becomes an upvalue
+            let index = self.meta_methods.remove(&MetaMethod::Index);
+            let getters = self.getters;
+            state.push_raw_function(
+                move |state| index_fallback::<T>(state, &getters, index.as_deref()),
+                1,
+            )?;
+            check(l, shim::lua_rawsetfield(l, -2, c"__index".as_ptr()))?;
+
+            let newindex = self.meta_methods.remove(&MetaMethod::NewIndex);
+            if !self.setters.is_empty() || newindex.is_some() {
+                let setters = self.setters;
+                state.push_raw_function(
+                    move |state| newindex_fallback::<T>(state, &setters, newindex.as_deref()),
+                    0,
+                )?;
+                check(l, shim::lua_rawsetfield(l, -2, c"__newindex".as_ptr()))?;
+            }
+
+            for (meta, method) in self.meta_methods {
+                state.push_raw_function(method, 0)?;
+                check(l, shim::lua_rawsetfield(l, -2, meta.name().as_ptr()))?;
+            }
+        }
+        guard.commit();
+
+        Ok(())
+    }
+}
+
+/// Sets the field `name` of the table right below the value on top of the stack, popping the value
+unsafe fn raw_set_name(l: *mut lua_State, name: &str) -> Result<()> {
+    unsafe {
+        check(
+            l,
+            shim::lua_pushlstring(l, name.as_ptr().cast(), name.len()),
+        )?;
+        lua_insert(l, -2);
+        check(l, shim::lua_rawset(l, -3))
+    }
+}
+
+/// The string at `idx`, if it is one
+unsafe fn string_key<'s>(l: *mut lua_State, idx: c_int) -> Option<&'s str> {
+    if unsafe { lua_type(l, idx) } != lua_Type::LUA_TSTRING.0 as c_int {
+        return None;
+    }
+
+    let mut len = 0;
+    let ptr = unsafe { lua_tolstring(l, idx, &mut len) };
+
+    str::from_utf8(unsafe { slice::from_raw_parts(ptr as *const u8, len) }).ok()
+}
+
+/// `__index`, looking up fields, then methods, then the custom metamethod, with the methods table as an upvalue
+fn index_fallback<T: UserData>(
+    state: &LuauState,
+    getters: &HashMap<String, Callback>,
+    index: Option<&(dyn Fn(&LuauState) -> Result<c_int> + Send)>,
+) -> Result<c_int> {
+    let l = state.as_ptr();
+    let key = unsafe { string_key(l, 2) };
+
+    if let Some(getter) = key.and_then(|key| getters.get(key)) {
+        return getter(state);
+    }
+
+    unsafe {
+        lua_pushvalue(l, 2);
+        if lua_rawget(l, lua_upvalueindex(3)) != lua_Type::LUA_TNIL.0 as c_int {
+            return Ok(1);
+        }
+        lua_pop(l, 1);
+    }
+
+    match index {
+        Some(index) => index(state),
+        None => Err(unknown_field::<T>(key)),
+    }
+}
+
+/// `__newindex` when there are field setters or a custom metamethod
+fn newindex_fallback<T: UserData>(
+    state: &LuauState,
+    setters: &HashMap<String, Callback>,
+    newindex: Option<&(dyn Fn(&LuauState) -> Result<c_int> + Send)>,
+) -> Result<c_int> {
+    let key = unsafe { string_key(state.as_ptr(), 2) };
+
+    if let Some(setter) = key.and_then(|key| setters.get(key)) {
+        return setter(state);
+    }
+
+    match newindex {
+        Some(newindex) => newindex(state),
+        None => Err(unknown_field::<T>(key)),
+    }
+}
+
+fn unknown_field<T: UserData>(key: Option<&str>) -> Error {
+    Error::UnknownField {
+        type_name: T::NAME,
+        field: key.unwrap_or("?").to_owned(),
+    }
+}
+
+/// Takes ownership of all arguments, splitting off the first one
+fn this_and_args<T: UserData>(state: &LuauState) -> Result<(AnyUserData, Vec<StackRef>)> {
+    let nargs = unsafe { lua_gettop(state.as_ptr()) };
+    let mut args = unsafe { StackRef::adopt_many(state, nargs) };
+
+    let this = match args.is_empty() {
+        true => StackRef::nil(state)?,
+        false => args.remove(0),
+    };
+    let this = AnyUserData::from_luau(this).map_err(|e| match e {
+        Error::FromLuau { from, .. } => Error::FromLuau { from, to: T::NAME },
+        e => e,
+    })?;
+
+    Ok((this, args))
+}
+
+fn method_callback<T, A, R, E, F>(
+    method: F,
+) -> impl Fn(&LuauState) -> Result<c_int> + Send + 'static
+where
+    T: UserData,
+    A: FromLuauArgs,
+    R: IntoLuauMulti,
+    E: Into<Box<dyn error::Error + Send + Sync>>,
+    F: Fn(&LuauState, &T, A) -> Result<R, E>
+        + for<'a> Fn(&'a LuauState, &T, A::Args<'a>) -> Result<R, E>
+        + Send
+        + 'static,
+{
+    move |state: &LuauState| {
+        let (this, args) = this_and_args::<T>(state)?;
+        let args = <A::Args<'_> as FromLuauMulti>::from_luau_multi(state, args)?;
+
+        let results = call_method(&method, state, &*this.borrow::<T>()?, args)
+            .map_err(|e| Error::from_callback(e.into()))?;
+
+        push_results(state, results)
+    }
+}
+
+fn method_mut_callback<T, A, R, E, F>(
+    method: F,
+) -> impl Fn(&LuauState) -> Result<c_int> + Send + 'static
+where
+    T: UserData,
+    A: FromLuauArgs,
+    R: IntoLuauMulti,
+    E: Into<Box<dyn error::Error + Send + Sync>>,
+    F: Fn(&LuauState, &mut T, A) -> Result<R, E>
+        + for<'a> Fn(&'a LuauState, &mut T, A::Args<'a>) -> Result<R, E>
+        + Send
+        + 'static,
+{
+    move |state: &LuauState| {
+        let (this, args) = this_and_args::<T>(state)?;
+        let args = <A::Args<'_> as FromLuauMulti>::from_luau_multi(state, args)?;
+
+        let results = call_method(&method, state, &mut *this.borrow_mut::<T>()?, args)
+            .map_err(|e| Error::from_callback(e.into()))?;
+
+        push_results(state, results)
+    }
+}
+
+/// Calls `method`, picking the `Fn` impl that takes the arguments for the state over the one that takes `A`
+fn call_method<'a, S, A, R>(
+    method: &impl Fn(&'a LuauState, S, A) -> R,
+    state: &'a LuauState,
+    this: S,
+    args: A,
+) -> R {
+    method(state, this, args)
+}
+
+impl LuauState {
+    /// Moves the value into a new luau userdata
+    pub fn create_userdata<T: UserData>(&self, value: T) -> Result<AnyUserData> {
+        self.reserve(1)?;
+        value.push(self)?;
+
+        Ok(AnyUserData {
+            value: unsafe { StackRef::adopt(self) },
+        })
+    }
+    /// Registers `T` ahead of time, which otherwise happens when the first value of the type is pushed
+    ///
+    /// Types get tags in the order they are registered, so registering all of them up front makes
+    /// [`LuauState::userdata_types`] deterministic.
+    pub fn register_userdata<T: UserData>(&self) -> Result<()> {
+        self.userdata_tag::<T>().map(|_| ())
+    }
+    /// Names of the registered userdata types, ordered by their tags
+    ///
+    /// Pass this to [`CompilerOptions::set_userdata_types`](luau_compiler::CompilerOptions::set_userdata_types)
+    /// so that the type information generated by the compiler refers to the same types as the tags in this state.
+    pub fn userdata_types(&self) -> Vec<&'static str> {
+        self.data().userdata.borrow().names().to_vec()
+    }
+    /// Returns the userdata tag of `T`, setting up its metatable and destructor on first use
+    pub(crate) fn userdata_tag<T: UserData>(&self) -> Result<c_int> {
+        unsafe extern "C" fn dtor<T>(_l: *mut lua_State, ud: *mut c_void) {
+            // can't unwind into luau
+            let _ = catch_unwind(AssertUnwindSafe(|| unsafe {
+                ptr::drop_in_place(ud as *mut RefCell<T>)
+            }));
+        }
+
+        let registry = &self.data().userdata;
+        if let Some(tag) = registry.borrow().tag::<T>() {
+            return Ok(tag);
+        }
+
+        let tag = registry
+            .borrow()
+            .next_tag()
+            .ok_or(Error::TooManyUserDataTypes)?;
+
+        let mut methods = UserDataMethods::<T>::new();
+        T::register(&mut methods);
+        methods.push_metatable(self)?;
+
+        let l = self.as_ptr();
+        unsafe {
+            lua_setuserdatametatable(l, tag);
+            lua_setuserdatadtor(l, tag, Some(dtor::<T>));
+        }
+
+        registry.borrow_mut().insert::<T>(tag, T::NAME);
+
+        Ok(tag)
+    }
+}
+
+impl<T: UserData> IntoLuau for T {
+    fn push(self, state: &LuauState) -> Result<()> {
+        const {
+            // luau only aligns userdata memory to 8 bytes
+            assert!(
+                align_of::<RefCell<T>>() <= 8,
+                "userdata types can't have an alignment above 8"
+            )
+        };
+
+        let tag = state.userdata_tag::<T>()?;
+        let l = state.as_ptr();
+
+        let mut ud = null_mut();
+        unsafe {
+            check(
+                l,
+                shim::lua_newuserdatataggedwithmetatable(l, size_of::<RefCell<T>>(), tag, &mut ud),
+            )?;
+            ptr::write(ud as *mut RefCell<T>, RefCell::new(self));
+        }
+
+        Ok(())
+    }
+}
+impl<'a, T: UserData + Clone> FromLuau<'a> for T {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        let from = value.type_name();
+        let ud =
+            AnyUserData::from_luau(value).map_err(|_| Error::FromLuau { from, to: T::NAME })?;
+
+        Ok(ud.borrow::<T>()?.clone())
+    }
+}
+impl<T: UserData + Clone> FromLuauArg for T {
+    type Arg<'a> = T;
+}
+
+/// Handle to a luau userdata of any type
+pub struct AnyUserData<'a> {
+    pub(crate) value: StackRef<'a>,
+}
+
+impl AnyUserData<'_> {
+    /// Name of the rust type that the userdata holds, if any
+    pub fn type_name(&self) -> Option<&'static str> {
+        let state = self.value.state();
+        let tag = unsafe { lua_userdatatag(state.as_ptr(), self.value.index()) };
+
+        state.data().userdata.borrow().name(tag)
+    }
+    /// Whether the userdata holds a `T`
+    pub fn is<T: UserData>(&self) -> bool {
+        self.cell::<T>().is_some()
+    }
+    /// Immutably borrows the value, failing if it's not a `T` or if it's mutably borrowed
+    pub fn borrow<T: UserData>(&self) -> Result<Ref<'_, T>> {
+        self.expect_cell::<T>()?
+            .try_borrow()
+            .map_err(|_| Error::UserDataBorrow(T::NAME))
+    }
+    /// Mutably borrows the value, failing if it's not a `T` or if it's borrowed
+    pub fn borrow_mut<T: UserData>(&self) -> Result<RefMut<'_, T>> {
+        self.expect_cell::<T>()?
+            .try_borrow_mut()
+            .map_err(|_| Error::UserDataBorrowMut(T::NAME))
+    }
+    fn cell<T: UserData>(&self) -> Option<&RefCell<T>> {
+        let state = self.value.state();
+
+        // no value of this type was ever created otherwise
+        let tag = state.data().userdata.borrow().tag::<T>()?;
+
+        let ptr = unsafe { lua_touserdatatagged(state.as_ptr(), self.value.index(), tag) };
+        if ptr.is_null() {
+            return None;
+        }
+
+        Some(unsafe { &*(ptr as *const RefCell<T>) })
+    }
+    fn expect_cell<T: UserData>(&self) -> Result<&RefCell<T>> {
+        self.cell::<T>().ok_or_else(|| Error::FromLuau {
+            from: self.type_name().unwrap_or("userdata"),
+            to: T::NAME,
+        })
+    }
+}
+
+impl IntoLuau for AnyUserData<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        self.value.push(state)
+    }
+}
+impl IntoLuau for &AnyUserData<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        (&self.value).push(state)
+    }
+}
+impl<'a> FromLuau<'a> for AnyUserData<'a> {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        expect_type(&value, lua_Type::LUA_TUSERDATA, "AnyUserData")?;
+
+        Ok(AnyUserData { value })
+    }
+}
+impl FromLuauArg for AnyUserData<'_> {
+    type Arg<'a> = AnyUserData<'a>;
+}
+
+impl PartialEq for AnyUserData<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = (&self.value, &other.value);
+
+        a.state().as_ptr() == b.state().as_ptr()
+            && unsafe { lua_rawequal(a.state().as_ptr(), a.index(), b.index()) != 0 }
+    }
+}
+
+impl Debug for AnyUserData<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ptr = unsafe { lua_topointer(self.value.state().as_ptr(), self.value.index()) };
+
+        write!(f, "<Luau UserData {ptr:p}>")
+    }
+}
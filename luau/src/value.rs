@@ -0,0 +1,125 @@
+use crate::{
+    buffer::Buffer,
+    convert::{type_of, FromLuau, FromLuauArg, IntoLuau},
+    error::{Error, Result},
+    function::Function,
+    stack::StackRef,
+    state::LuauState,
+    string::LuauString,
+    table::Table,
+    thread::Thread,
+    userdata::AnyUserData,
+    vector::VECTOR_SIZE,
+};
+use luau_sys::{
+    vector::{push_vector, to_vector},
+    vm::{
+        lua_Type, lua_pushlightuserdatatagged, lua_pushnil, lua_pushnumber, lua_toboolean,
+        lua_tolightuserdata, lua_tonumberx,
+    },
+};
+use std::{ffi::c_void, ptr::null_mut};
+
+/// Largest integer that can be represented exactly by luau numbers (2^53 - 1)
+pub const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+/// Any luau value
+#[derive(Debug)]
+pub enum Value<'a> {
+    Nil,
+    Boolean(bool),
+    /// Luau numbers are always `f64`, see [`Value::as_integer`]
+    Number(f64),
+    /// See [`Vector`](crate::vector::Vector) for conversions
+    Vector([f32; VECTOR_SIZE]),
+    String(LuauString<'a>),
+    Table(Table<'a>),
+    Function(Function<'a>),
+    UserData(AnyUserData<'a>),
+    LightUserData(*mut c_void),
+    Thread(Thread<'a>),
+    Buffer(Buffer<'a>),
+}
+
+impl<'a> Value<'a> {
+    /// Name of the luau type of the value
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Boolean(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::Vector(_) => "vector",
+            Value::String(_) => "string",
+            Value::Table(_) => "table",
+            Value::Function(_) => "function",
+            Value::UserData(_) => "userdata",
+            Value::LightUserData(_) => "userdata",
+            Value::Thread(_) => "thread",
+            Value::Buffer(_) => "buffer",
+        }
+    }
+    pub fn is_nil(&self) -> bool {
+        matches!(self, Value::Nil)
+    }
+    /// Returns the number if it's an integer that can be represented exactly
+    pub fn as_integer(&self) -> Option<i64> {
+        match *self {
+            Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER => Some(n as i64),
+            _ => None,
+        }
+    }
+}
+
+impl IntoLuau for Value<'_> {
+    fn push(self, state: &LuauState) -> Result<()> {
+        let l = state.as_ptr();
+
+        match self {
+            Value::Nil => unsafe { lua_pushnil(l) },
+            Value::Boolean(b) => return b.push(state),
+            Value::Number(n) => unsafe { lua_pushnumber(l, n) },
+            Value::Vector(v) => unsafe { push_vector(l, v) },
+            Value::String(s) => return s.push(state),
+            Value::Table(t) => return t.push(state),
+            Value::Function(f) => return f.push(state),
+            Value::UserData(u) => return u.push(state),
+            Value::Thread(t) => return t.push(state),
+            Value::Buffer(b) => return b.push(state),
+            Value::LightUserData(p) => unsafe { lua_pushlightuserdatatagged(l, p, 0) },
+        }
+
+        Ok(())
+    }
+}
+impl<'a> FromLuau<'a> for Value<'a> {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        let l = value.state().as_ptr();
+        let i = value.index();
+
+        Ok(match type_of(&value) {
+            lua_Type::LUA_TNIL => Value::Nil,
+            lua_Type::LUA_TBOOLEAN => Value::Boolean(unsafe { lua_toboolean(l, i) } != 0),
+            lua_Type::LUA_TLIGHTUSERDATA => {
+                Value::LightUserData(unsafe { lua_tolightuserdata(l, i) })
+            }
+            lua_Type::LUA_TNUMBER => Value::Number(unsafe { lua_tonumberx(l, i, null_mut()) }),
+            // checked the type
+            lua_Type::LUA_TVECTOR => Value::Vector(unsafe { to_vector(l, i) }.unwrap()),
+            lua_Type::LUA_TSTRING => Value::String(LuauString { value }),
+            lua_Type::LUA_TTABLE => Value::Table(Table { value }),
+            lua_Type::LUA_TFUNCTION => Value::Function(Function { value }),
+            lua_Type::LUA_TUSERDATA => Value::UserData(AnyUserData { value }),
+            lua_Type::LUA_TTHREAD => Value::Thread(Thread::from_luau(value)?),
+            lua_Type::LUA_TBUFFER => Value::Buffer(Buffer { value }),
+            _ => {
+                return Err(Error::FromLuau {
+                    from: value.type_name(),
+                    to: "Value",
+                });
+            }
+        })
+    }
+}
+impl FromLuauArg for Value<'_> {
+    type Arg<'a> = Value<'a>;
+}
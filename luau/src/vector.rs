@@ -0,0 +1,194 @@
+use crate::{
+    convert::{expect_type, FromLuau, FromLuauArg, IntoLuau},
+    error::Result,
+    stack::StackRef,
+    state::LuauState,
+};
+use luau_compiler::Constant;
+use luau_sys::{
+    vector::{push_vector, to_vector},
+    vm::lua_Type,
+};
+use std::ops::{Index, IndexMut};
+
+/// Number of components of luau vectors, 3 unless luau was built with the `LUAU_VECTOR_SIZE=4` environment variable
+pub const VECTOR_SIZE: usize = luau_sys::VECTOR_SIZE;
+
+/// A luau vector, with [`VECTOR_SIZE`] components
+///
+/// Converts from and to both `[f32; 3]` and `[f32; 4]`, a missing fourth component is 0 and an extra one is dropped.
+/// With the `glam` and `mint` features the 3 and 4 component vectors of those crates convert the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vector(pub [f32; VECTOR_SIZE]);
+
+impl Vector {
+    /// Creates a vector, the fourth component is 0 if there is one
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self::from([x, y, z])
+    }
+    pub fn x(&self) -> f32 {
+        self.0[0]
+    }
+    pub fn y(&self) -> f32 {
+        self.0[1]
+    }
+    pub fn z(&self) -> f32 {
+        self.0[2]
+    }
+    /// The fourth component, always 0 with 3 component vectors
+    pub fn w(&self) -> f32 {
+        self.0.get(3).copied().unwrap_or(0.0)
+    }
+}
+
+impl From<[f32; 3]> for Vector {
+    fn from([x, y, z]: [f32; 3]) -> Self {
+        Self::from([x, y, z, 0.0])
+    }
+}
+impl From<[f32; 4]> for Vector {
+    fn from(v: [f32; 4]) -> Self {
+        let mut components = [0.0; VECTOR_SIZE];
+        components.copy_from_slice(&v[..VECTOR_SIZE]);
+
+        Self(components)
+    }
+}
+impl From<Vector> for [f32; 3] {
+    fn from(v: Vector) -> Self {
+        [v.x(), v.y(), v.z()]
+    }
+}
+impl From<Vector> for [f32; 4] {
+    fn from(v: Vector) -> Self {
+        [v.x(), v.y(), v.z(), v.w()]
+    }
+}
+/// For library members known to the compiler, see [`LibraryWithKnownMembers`](luau_compiler::LibraryWithKnownMembers)
+impl From<Vector> for Constant {
+    fn from(v: Vector) -> Self {
+        Constant::Vector(v.x(), v.y(), v.z(), v.w())
+    }
+}
+
+impl Index<usize> for Vector {
+    type Output = f32;
+
+    fn index(&self, index: usize) -> &f32 {
+        &self.0[index]
+    }
+}
+impl IndexMut<usize> for Vector {
+    fn index_mut(&mut self, index: usize) -> &mut f32 {
+        &mut self.0[index]
+    }
+}
+
+impl IntoLuau for Vector {
+    fn push(self, state: &LuauState) -> Result<()> {
+        unsafe { push_vector(state.as_ptr(), self.0) };
+
+        Ok(())
+    }
+}
+impl<'a> FromLuau<'a> for Vector {
+    fn from_luau(value: StackRef<'a>) -> Result<Self> {
+        expect_type(&value, lua_Type::LUA_TVECTOR, "Vector")?;
+
+        // checked the type
+        Ok(Vector(
+            unsafe { to_vector(value.state().as_ptr(), value.index()) }.unwrap(),
+        ))
+    }
+}
+impl FromLuauArg for Vector {
+    type Arg<'a> = Vector;
+}
+
+/// Vectors of other crates that convert through [`Vector`]
+macro_rules! impl_via_vector {
+    ($($t:ty),* $(,)?) => {$(
+        impl IntoLuau for $t {
+            fn push(self, state: &LuauState) -> Result<()> {
+                Vector::from(self).push(state)
+            }
+        }
+        impl<'a> FromLuau<'a> for $t {
+            fn from_luau(value: StackRef<'a>) -> Result<Self> {
+                Vector::from_luau(value).map(Into::into)
+            }
+        }
+        impl FromLuauArg for $t {
+            type Arg<'a> = $t;
+        }
+    )*};
+}
+impl_via_vector!([f32; 3], [f32; 4]);
+
+#[cfg(feature = "glam")]
+mod glam_impls {
+    use super::*;
+    use glam::{Vec3, Vec3A, Vec4};
+
+    impl From<Vec3> for Vector {
+        fn from(v: Vec3) -> Self {
+            Self::from(v.to_array())
+        }
+    }
+    impl From<Vec3A> for Vector {
+        fn from(v: Vec3A) -> Self {
+            Self::from(v.to_array())
+        }
+    }
+    impl From<Vec4> for Vector {
+        fn from(v: Vec4) -> Self {
+            Self::from(v.to_array())
+        }
+    }
+    impl From<Vector> for Vec3 {
+        fn from(v: Vector) -> Self {
+            Vec3::from_array(v.into())
+        }
+    }
+    impl From<Vector> for Vec3A {
+        fn from(v: Vector) -> Self {
+            Vec3A::from_array(v.into())
+        }
+    }
+    impl From<Vector> for Vec4 {
+        fn from(v: Vector) -> Self {
+            Vec4::from_array(v.into())
+        }
+    }
+
+    impl_via_vector!(Vec3, Vec3A, Vec4);
+}
+
+#[cfg(feature = "mint")]
+mod mint_impls {
+    use super::*;
+    use mint::{Vector3, Vector4};
+
+    impl From<Vector3<f32>> for Vector {
+        fn from(v: Vector3<f32>) -> Self {
+            Self::from(<[f32; 3]>::from(v))
+        }
+    }
+    impl From<Vector4<f32>> for Vector {
+        fn from(v: Vector4<f32>) -> Self {
+            Self::from(<[f32; 4]>::from(v))
+        }
+    }
+    impl From<Vector> for Vector3<f32> {
+        fn from(v: Vector) -> Self {
+            <[f32; 3]>::from(v).into()
+        }
+    }
+    impl From<Vector> for Vector4<f32> {
+        fn from(v: Vector) -> Self {
+            <[f32; 4]>::from(v).into()
+        }
+    }
+
+    impl_via_vector!(Vector3<f32>, Vector4<f32>);
+}
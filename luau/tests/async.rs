@@ -0,0 +1,88 @@
+mod common;
+
+use common::load;
+use luau::{state::LuauState, Error};
+use std::{
+    future::Future,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake},
+    thread::{self, Thread},
+};
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Arc::new(Unpark(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// Returns pending once before completing
+async fn yield_now() {
+    let mut yielded = false;
+    std::future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+#[test]
+fn test_async_function() {
+    let state = LuauState::new().unwrap();
+
+    let sleep_double = state
+        .create_async_function(|x: f64| async move {
+            yield_now().await;
+            Ok::<_, Error>(x * 2.0)
+        })
+        .unwrap();
+    let script = load(
+        &state,
+        "local f = ... local a = f(1) local b = f(a) return a + b",
+    );
+
+    let thread = state.create_thread(&script).unwrap();
+    let result: f64 = block_on(thread.into_future(&sleep_double)).unwrap();
+    assert_eq!(result, 6.0);
+
+    // can't yield outside of a thread
+    assert!(sleep_double.call::<_, f64>(1.0).is_err());
+}
+
+#[test]
+fn test_async_function_error() {
+    let state = LuauState::new().unwrap();
+
+    let fail = state
+        .create_async_function(|()| async move {
+            yield_now().await;
+            Err::<(), _>(Error::Callback("io failed".into()))
+        })
+        .unwrap();
+    let script = load(&state, "local f = ... f() return 1");
+
+    let thread = state.create_thread(&script).unwrap();
+    match block_on(thread.into_future::<_, f64>(&fail)) {
+        Err(Error::Callback(e)) => assert_eq!(e.to_string(), "io failed"),
+        other => panic!("expected the callback error, got {other:?}"),
+    }
+}
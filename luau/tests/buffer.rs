@@ -0,0 +1,93 @@
+use luau::{buffer::Buffer, function::Function, state::LuauState, Error, StdLib};
+use luau_compiler::CompilerOptions;
+
+#[test]
+fn test_buffer_bytes() {
+    let state = LuauState::new().unwrap();
+    state.open_libs(StdLib::BUFFER).unwrap();
+
+    let buffer = state.create_buffer(b"\x01\x02\x03").unwrap();
+    assert_eq!(buffer.len(), 3);
+    assert_eq!(&*buffer.borrow().unwrap(), b"\x01\x02\x03");
+
+    buffer.borrow_mut().unwrap()[0] = 10;
+
+    let sum: Function = state
+        .exec(
+            r#"return function(b)
+                local sum = 0
+                for i = 0, buffer.len(b) - 1 do
+                    sum += buffer.readu8(b, i)
+                end
+                buffer.writeu8(b, 0, 0)
+                return sum
+            end"#,
+            &CompilerOptions::new(),
+        )
+        .unwrap();
+    assert_eq!(sum.call::<_, u32>(&buffer).unwrap(), 15);
+    assert_eq!(buffer.to_vec().unwrap(), [0, 2, 3]);
+
+    let zeroed = state.create_buffer_zeroed(4).unwrap();
+    assert_eq!(zeroed.to_vec().unwrap(), [0; 4]);
+    assert_ne!(zeroed, buffer);
+}
+
+#[test]
+fn test_buffer_vec() {
+    let state = LuauState::new().unwrap();
+    state.open_libs(StdLib::BUFFER).unwrap();
+
+    let f: Function = state
+        .exec(
+            "return function(b) buffer.writeu8(b, 1, 42) return b, type(b) end",
+            &CompilerOptions::new(),
+        )
+        .unwrap();
+
+    let (bytes, ty): (Vec<u8>, String) = f.call(vec![1u8, 2, 3]).unwrap();
+    assert_eq!(bytes, [1, 42, 3]);
+    assert_eq!(ty, "buffer");
+
+    let (buffer, _): (Buffer, String) = f.call(&[0u8, 0][..]).unwrap();
+    assert_eq!(buffer.to_vec().unwrap(), [0, 42]);
+
+    // other integers still use tables
+    let (_, ty): (Vec<u16>, String) = state
+        .exec("return {1, 2}, type({})", &CompilerOptions::new())
+        .unwrap();
+    assert_eq!(ty, "table");
+}
+
+#[test]
+fn test_buffer_borrows() {
+    let state = LuauState::new().unwrap();
+
+    let buffer = state.create_buffer(b"abc").unwrap();
+    let f: Function = state
+        .exec("return function() end", &CompilerOptions::new())
+        .unwrap();
+    let table = state.create_table(1, 0).unwrap();
+    table.raw_set(1, &buffer).unwrap();
+
+    {
+        let bytes = buffer.borrow().unwrap();
+        let other = buffer.borrow().unwrap();
+        assert_eq!(*bytes, *other);
+
+        assert!(matches!(buffer.borrow_mut(), Err(Error::BufferBorrowMut)));
+        // luau could modify the bytes
+        assert!(matches!(f.call::<_, ()>(()), Err(Error::BorrowedBuffer)));
+    }
+
+    {
+        let _bytes = buffer.borrow_mut().unwrap();
+        assert!(matches!(buffer.borrow(), Err(Error::BufferBorrow)));
+
+        // other handles to the same buffer share the borrow
+        let copy: Buffer = table.raw_get(1).unwrap();
+        assert!(matches!(copy.borrow(), Err(Error::BufferBorrow)));
+    }
+
+    f.call::<_, ()>(()).unwrap();
+}
@@ -0,0 +1,22 @@
+//! Helpers shared by the integration tests
+
+// each test file uses only some of them
+#![allow(dead_code)]
+
+use luau::{function::Function, state::LuauState, UserData};
+use luau_compiler::{compile, CompilerOptions};
+use std::sync::Arc;
+
+/// Compiles the source code with the default options and loads it
+pub fn load<'a>(state: &'a LuauState, source: &str) -> Function<'a> {
+    let bytecode = compile(source, &CompilerOptions::new()).unwrap();
+
+    state.load("=test", &bytecode, None).unwrap()
+}
+
+/// Userdata holding a clone of the `Arc`, whose strong count tells whether luau dropped it
+pub struct Tracked(pub Arc<()>);
+
+impl UserData for Tracked {
+    const NAME: &'static str = "Tracked";
+}
@@ -0,0 +1,55 @@
+use luau::{allocator::LuauAllocatorDefault, state::LuauState, Error, StdLib};
+use luau_compiler::CompilerOptions;
+
+#[test]
+fn test_state_creation_error() {
+    let state = LuauState::new_with_alloc(LuauAllocatorDefault::new(Some(0)));
+
+    assert!(matches!(state, Err(Error::Memory)));
+}
+
+#[test]
+fn test_runtime_error() {
+    let state = LuauState::new().unwrap();
+
+    match state.exec::<()>("local t = nil; return t.field", &CompilerOptions::new()) {
+        Err(Error::Runtime { message, .. }) => assert!(message.contains("attempt to index nil")),
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_memory_error() {
+    let state = LuauState::new_with_alloc(LuauAllocatorDefault::new(Some(1 << 20))).unwrap();
+    state.open_libs(StdLib::BASE).unwrap();
+    let opts = CompilerOptions::new();
+
+    assert!(matches!(
+        state.exec::<()>("local t = {} for i = 1, 1e7 do t[i] = i end", &opts),
+        Err(Error::Memory)
+    ));
+
+    // an error with the same message is still a runtime error
+    match state.exec::<()>("error('not enough memory', 0)", &opts) {
+        Err(Error::Runtime { message, .. }) => assert_eq!(message, "not enough memory"),
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_conversion_error() {
+    let state = LuauState::new().unwrap();
+
+    let error = state
+        .exec::<f64>("return 'not a number'", &CompilerOptions::new())
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        Error::FromLuau {
+            from: "string",
+            to: "f64"
+        }
+    ));
+    assert_eq!(error.to_string(), "cannot convert luau string to f64");
+}
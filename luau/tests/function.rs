@@ -0,0 +1,162 @@
+use luau::{function::Function, state::LuauState, string::LuauString, table::Table, Error};
+use luau_compiler::CompilerOptions;
+use std::fmt::Display;
+
+#[derive(Debug)]
+struct CustomError;
+
+impl Display for CustomError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "custom error")
+    }
+}
+impl std::error::Error for CustomError {}
+
+#[test]
+fn test_rust_function() {
+    let state = LuauState::new().unwrap();
+
+    let add = state
+        .create_function(|_, (a, b): (f64, f64)| Ok::<_, Error>(a + b))
+        .unwrap();
+
+    assert_eq!(add.call::<_, f64>((1.0, 2.0)).unwrap(), 3.0);
+    assert!(matches!(
+        add.call::<_, f64>(("a", 2.0)),
+        Err(Error::FromLuau { .. })
+    ));
+}
+
+#[test]
+fn test_rust_function_handle_arguments() {
+    let state = LuauState::new().unwrap();
+
+    let sum = state
+        .create_function(|_, (t, key): (Table, LuauString)| {
+            let total = (1..=t.len())
+                .map(|i| t.raw_get::<_, f64>(i))
+                .sum::<Result<f64, _>>()?;
+            t.set(key, total)?;
+
+            Ok::<_, Error>(total)
+        })
+        .unwrap();
+
+    let source = "local sum = ... local t = {1, 2, 3} return sum(t, 'total'), t.total";
+    let bytecode = luau_compiler::compile(source, &CompilerOptions::new()).unwrap();
+    let chunk: Function = state.load("=test", &bytecode, None).unwrap();
+    assert_eq!(chunk.call::<_, (f64, f64)>(&sum).unwrap(), (6.0, 6.0));
+
+    assert!(matches!(
+        sum.call::<_, f64>((1.0, "total")),
+        Err(Error::FromLuau { to: "Table", .. })
+    ));
+}
+
+#[test]
+fn test_rust_function_error_preserved() {
+    let state = LuauState::new().unwrap();
+    let table = state.create_table(0, 0).unwrap();
+
+    let fail = state
+        .create_function(|_, ()| Err::<(), _>(CustomError))
+        .unwrap();
+    table.set("fail", &fail).unwrap();
+
+    match fail.call::<_, ()>(()) {
+        Err(Error::Callback(e)) => assert!(e.downcast_ref::<CustomError>().is_some()),
+        other => panic!("expected a callback error, got {other:?}"),
+    }
+
+    // the error object travels through luau code unchanged
+    let bytecode =
+        luau_compiler::compile("local t = ... t.fail()", &CompilerOptions::new()).unwrap();
+    let chunk: Function = state.load("=test", &bytecode, None).unwrap();
+    assert!(matches!(
+        chunk.call::<_, ()>(&table),
+        Err(Error::Callback(_))
+    ));
+}
+
+#[test]
+fn test_rust_function_panic() {
+    let state = LuauState::new().unwrap();
+
+    let boom = state
+        .create_function(|_, ()| -> Result<(), Error> { panic!("boom") })
+        .unwrap();
+
+    match boom.call::<_, ()>(()) {
+        Err(Error::Panic(msg)) => assert_eq!(msg, "boom"),
+        other => panic!("expected a panic error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_call_traceback() {
+    let state = LuauState::new().unwrap();
+
+    let source = r#"
+        local function inner()
+            local t = nil
+            return t.field
+        end
+        local function outer()
+            return inner()
+        end
+        return outer
+    "#;
+    let bytecode = luau_compiler::compile(source, &CompilerOptions::new()).unwrap();
+    let chunk: Function = state.load("=test", &bytecode, None).unwrap();
+    let outer: Function = chunk.call(()).unwrap();
+
+    match outer.call::<_, ()>(()) {
+        Err(Error::Runtime {
+            message,
+            traceback: Some(traceback),
+        }) => {
+            assert!(message.starts_with("test:4:"));
+            assert!(traceback.contains("test:4 function inner"));
+            assert!(traceback.contains("test:7 function outer"));
+        }
+        other => panic!("expected a runtime error with a traceback, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_call_with_handler() {
+    let state = LuauState::new().unwrap();
+
+    let bytecode =
+        luau_compiler::compile("local t = nil return t.field", &CompilerOptions::new()).unwrap();
+    let chunk: Function = state.load("=test", &bytecode, None).unwrap();
+
+    let result = chunk.call_with_handler::<_, ()>((), |_, error| {
+        Error::Callback(format!("handled: {error}").into())
+    });
+    match result {
+        Err(Error::Callback(e)) => assert!(e.to_string().starts_with("handled: runtime error")),
+        other => panic!("expected the handler's error, got {other:?}"),
+    }
+
+    // the handler can use the state
+    let result = chunk.call_with_handler::<_, ()>((), |state, error| {
+        let table = state.create_table(0, 1).unwrap();
+        table.set("message", error.to_string()).unwrap();
+
+        Error::Callback(table.get::<_, String>("message").unwrap().into())
+    });
+    match result {
+        Err(Error::Callback(e)) => assert!(e.to_string().starts_with("runtime error")),
+        other => panic!("expected the handler's error, got {other:?}"),
+    }
+
+    // returning the error unchanged
+    assert!(matches!(
+        chunk.call_with_handler::<_, ()>((), |_, e| e),
+        Err(Error::Runtime {
+            traceback: None,
+            ..
+        })
+    ));
+}
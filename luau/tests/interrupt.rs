@@ -0,0 +1,206 @@
+mod common;
+
+use common::load;
+use luau::{
+    interrupt::{self, CancellationToken, InterruptAction},
+    load::LoadError,
+    state::LuauState,
+    thread::ThreadResult,
+    value::Value,
+    Error,
+};
+use luau_compiler::{compile, CompilerOptions};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn test_safepoint_budget() {
+    let state = LuauState::new().unwrap();
+    state.set_interrupt(interrupt::safepoint_budget(1000));
+
+    let short = load(&state, "local x = 0 for i = 1, 10 do x += i end return x");
+    assert_eq!(short.call::<_, f64>(()).unwrap(), 55.0);
+
+    let spin = load(&state, "while true do end");
+    assert!(matches!(
+        spin.call::<_, ()>(()),
+        Err(Error::Interrupted("safepoint budget exhausted"))
+    ));
+
+    state.remove_interrupt();
+    assert!(short.call::<_, f64>(()).is_ok());
+}
+
+#[test]
+fn test_deadline() {
+    let state = LuauState::new().unwrap();
+    state.set_interrupt(interrupt::deadline(
+        Instant::now() + Duration::from_millis(50),
+    ));
+
+    let spin = load(&state, "while true do end");
+    assert!(matches!(
+        spin.call::<_, ()>(()),
+        Err(Error::Interrupted("deadline exceeded"))
+    ));
+}
+
+#[test]
+fn test_cancellation_token() {
+    let state = LuauState::new().unwrap();
+    let token = CancellationToken::new();
+    state.set_interrupt(token.interrupt());
+
+    let canceller = {
+        let token = token.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            token.cancel();
+        })
+    };
+
+    let spin = load(&state, "while true do end");
+    assert!(matches!(
+        spin.call::<_, ()>(()),
+        Err(Error::Interrupted("cancelled"))
+    ));
+    assert!(token.is_cancelled());
+
+    canceller.join().unwrap();
+}
+
+#[test]
+fn test_interrupt_yield() {
+    let state = LuauState::new().unwrap();
+
+    let mut count = 0;
+    state.set_interrupt(move |_, gc| {
+        if gc.is_some() {
+            return InterruptAction::Continue;
+        }
+
+        count += 1;
+        match count % 10 {
+            0 => InterruptAction::Yield,
+            _ => InterruptAction::Continue,
+        }
+    });
+
+    let script = load(&state, "local x = 0 for i = 1, 100 do x += i end return x");
+    let thread = state.create_thread(&script).unwrap();
+
+    let mut yields = 0;
+    let result = loop {
+        match thread.resume::<_, Option<f64>>(()) {
+            ThreadResult::Yielded(_) => yields += 1,
+            ThreadResult::Finished(x) => break x,
+            ThreadResult::Error(e) => panic!("{e}"),
+        }
+    };
+    assert_eq!(result, Some(5050.0));
+    assert!(yields > 0);
+}
+
+#[test]
+fn test_interrupt_error_during_gc() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    let state = LuauState::new().unwrap();
+
+    let bytecode = compile("return", &CompilerOptions::new()).unwrap().to_vec();
+    let gc_steps = Arc::new(AtomicUsize::new(0));
+    state.set_interrupt({
+        let gc_steps = gc_steps.clone();
+        move |state, gc| {
+            if gc.is_some() {
+                gc_steps.fetch_add(1, Ordering::Relaxed);
+                // the state can't be used in the middle of a step
+                assert!(matches!(state.create_table(0, 0), Err(Error::GcStep)));
+                assert!(matches!(
+                    state.load("=gc", &bytecode, None),
+                    Err(LoadError::GcStep)
+                ));
+            }
+
+            InterruptAction::Error(Error::Interrupted("always"))
+        }
+    });
+
+    // allocating from rust runs garbage collection steps without any other safepoints, the errors returned
+    // for them must not touch the stack or stop anything
+    let table = state.create_table(0, 0).unwrap();
+    for i in 1..=20_000 {
+        table.set(i, format!("value {i}")).unwrap();
+        let _garbage = state.create_table(8, 8).unwrap();
+    }
+    assert!(gc_steps.load(Ordering::Relaxed) > 0);
+    for i in (1..=20_000).step_by(997) {
+        assert_eq!(table.get::<_, String>(i).unwrap(), format!("value {i}"));
+    }
+
+    // at a safepoint the error is raised as usual
+    let spin = load(&state, "while true do end");
+    assert!(matches!(
+        spin.call::<_, ()>(()),
+        Err(Error::Interrupted("always"))
+    ));
+
+    state.remove_interrupt();
+    let sum = load(&state, "local x = 0 for i = 1, 10 do x += i end return x");
+    assert_eq!(sum.call::<_, f64>(()).unwrap(), 55.0);
+}
+
+#[test]
+fn test_interrupt_panic_during_gc() {
+    let state = LuauState::new().unwrap();
+    state.set_interrupt(|_, gc| {
+        if gc.is_some() {
+            panic!("gc step");
+        }
+        InterruptAction::Continue
+    });
+
+    // the panic is kept until the next safepoint of luau code
+    let table = state.create_table(0, 0).unwrap();
+    for i in 1..=20_000 {
+        table.set(i, format!("value {i}")).unwrap();
+    }
+    assert_eq!(table.get::<_, String>(1).unwrap(), "value 1");
+
+    let spin = load(&state, "while true do end");
+    match spin.call::<_, ()>(()) {
+        Err(Error::Panic(message)) => assert_eq!(message, "gc step"),
+        other => panic!("expected the panic, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_interrupt_uses_state() {
+    let state = LuauState::new().unwrap();
+
+    state.set_interrupt(|state, gc| {
+        if gc.is_some() {
+            return InterruptAction::Continue;
+        }
+
+        let globals = state.globals().unwrap();
+        if globals.get::<_, Value>("fromInterrupt").unwrap().is_nil() {
+            let table = state.create_table(0, 1).unwrap();
+            table.set("x", 42.0).unwrap();
+            globals.set("fromInterrupt", table).unwrap();
+        }
+
+        InterruptAction::Continue
+    });
+
+    let spin = load(
+        &state,
+        "local a, b = 1, 2 while not fromInterrupt do end return fromInterrupt.x + a + b",
+    );
+    assert_eq!(spin.call::<_, f64>(()).unwrap(), 45.0);
+}
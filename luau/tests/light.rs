@@ -0,0 +1,86 @@
+use luau::{
+    light::{LightRef, LightUserData},
+    state::LuauState,
+    value::Value,
+    Error,
+};
+use luau_compiler::{compile, CompilerOptions};
+
+/// Only ever referenced by index
+struct Entity;
+
+impl LightUserData for Entity {
+    const NAME: &'static str = "Entity";
+}
+
+struct Texture {
+    id: u32,
+}
+
+impl LightUserData for Texture {
+    const NAME: &'static str = "Texture";
+}
+
+#[test]
+fn test_light_ref() {
+    let state = LuauState::new().unwrap();
+    let table = state.create_table(0, 0).unwrap();
+
+    let entity = LightRef::<Entity>::from_index(42);
+    table.set("entity", entity).unwrap();
+
+    let back: LightRef<Entity> = table.get("entity").unwrap();
+    assert_eq!(back, entity);
+    assert_eq!(back.index(), 42);
+
+    let texture = Texture { id: 7 };
+    table.set("texture", LightRef::from_ptr(&texture)).unwrap();
+
+    let back: LightRef<Texture> = table.get("texture").unwrap();
+    assert_eq!(unsafe { (*back.as_ptr()).id }, 7);
+
+    // plain light userdata is still available
+    let raw: Value = table.get("entity").unwrap();
+    assert!(matches!(raw, Value::LightUserData(_)));
+}
+
+#[test]
+fn test_light_ref_tag_checked() {
+    let state = LuauState::new().unwrap();
+    let table = state.create_table(0, 0).unwrap();
+
+    table
+        .set("entity", LightRef::<Entity>::from_index(0))
+        .unwrap();
+    table
+        .set("untagged", Value::LightUserData(std::ptr::null_mut()))
+        .unwrap();
+
+    assert!(matches!(
+        table.get::<_, LightRef<Texture>>("entity"),
+        Err(Error::FromLuau {
+            from: "Entity",
+            to: "Texture"
+        })
+    ));
+    assert!(matches!(
+        table.get::<_, LightRef<Entity>>("untagged"),
+        Err(Error::FromLuau {
+            from: "userdata",
+            to: "Entity"
+        })
+    ));
+    assert!(matches!(
+        table.get::<_, LightRef<Entity>>("missing"),
+        Err(Error::FromLuau {
+            from: "nil",
+            to: "Entity"
+        })
+    ));
+
+    // survives a trip through luau code
+    let bytecode = compile("local e = ... return e", &CompilerOptions::new()).unwrap();
+    let identity = state.load("=test", &bytecode, None).unwrap();
+    let entity: LightRef<Entity> = identity.call(LightRef::<Entity>::from_index(3)).unwrap();
+    assert_eq!(entity.index(), 3);
+}
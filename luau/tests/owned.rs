@@ -0,0 +1,122 @@
+mod common;
+
+use common::Tracked;
+use luau::{
+    function::Function,
+    owned::{OwnedFunction, OwnedTable},
+    state::LuauState,
+    table::Table,
+    Error, StdLib,
+};
+use luau_compiler::CompilerOptions;
+use std::{sync::Arc, thread};
+
+/// Event handlers stored on the rust side
+struct Handlers {
+    on_event: OwnedFunction,
+    config: OwnedTable,
+}
+
+fn register(state: &LuauState) -> Handlers {
+    let (on_event, config): (Function, Table) = state
+        .exec(
+            "local config = { factor = 2 } return function(x) return x * config.factor end, config",
+            &CompilerOptions::new(),
+        )
+        .unwrap();
+
+    Handlers {
+        on_event: on_event.into_owned().unwrap(),
+        config: config.into_owned().unwrap(),
+    }
+}
+
+#[test]
+fn test_owned_refs() {
+    let state = LuauState::new().unwrap();
+    let handlers = register(&state);
+
+    assert_eq!(handlers.on_event.call::<_, f64>(&state, 5.0).unwrap(), 10.0);
+
+    handlers
+        .config
+        .get(&state)
+        .unwrap()
+        .set("factor", 3.0)
+        .unwrap();
+    assert_eq!(handlers.on_event.call::<_, f64>(&state, 5.0).unwrap(), 15.0);
+
+    // usable from callbacks too
+    let on_event = handlers.on_event;
+    let call = state
+        .create_function(move |state, x: f64| on_event.call::<_, f64>(state, x))
+        .unwrap();
+    assert_eq!(call.call::<_, f64>(1.0).unwrap(), 3.0);
+
+    let any = state.create_ref("hello").unwrap();
+    assert_eq!(any.get::<String>(&state).unwrap(), "hello");
+    drop(any);
+    state.release_refs();
+}
+
+#[test]
+fn test_owned_ref_released_on_drop() {
+    let counter = Arc::new(());
+    let state = LuauState::new().unwrap();
+    state.open_libs(StdLib::BASE).unwrap();
+    let collect = || {
+        state
+            .exec::<()>("collectgarbage('collect')", &CompilerOptions::new())
+            .unwrap()
+    };
+
+    // dropped on the thread using the state, released right away
+    let owned = state.create_ref(Tracked(counter.clone())).unwrap();
+    drop(owned);
+    collect();
+    assert_eq!(Arc::strong_count(&counter), 1);
+
+    // another thread can't use the state, so the reference waits for the state to release it
+    let owned = state.create_ref(Tracked(counter.clone())).unwrap();
+    thread::spawn(move || drop(owned)).join().unwrap();
+    collect();
+    assert_eq!(Arc::strong_count(&counter), 2);
+
+    state.release_refs();
+    collect();
+    assert_eq!(Arc::strong_count(&counter), 1);
+}
+
+#[test]
+fn test_owned_ref_outlives_state() {
+    let state = LuauState::new().unwrap();
+    let table = state.create_table(0, 0).unwrap().into_owned().unwrap();
+
+    drop(state);
+    // nothing to release anymore
+    drop(table);
+}
+
+#[test]
+fn test_owned_ref_wrong_type() {
+    let state = LuauState::new().unwrap();
+    let number = state.create_ref(1.0).unwrap();
+
+    assert!(matches!(
+        number.get::<Table>(&state),
+        Err(Error::FromLuau { .. })
+    ));
+}
+
+#[test]
+fn test_owned_ref_foreign_state() {
+    let state = LuauState::new().unwrap();
+    let other = LuauState::new().unwrap();
+    let number = state.create_ref(1.0).unwrap();
+
+    assert!(matches!(number.get::<f64>(&other), Err(Error::ForeignRef)));
+
+    let table = other.create_table(0, 1).unwrap();
+    assert!(matches!(table.set("x", &number), Err(Error::ForeignRef)));
+    assert_eq!(number.get::<f64>(&state).unwrap(), 1.0);
+}
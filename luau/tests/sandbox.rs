@@ -0,0 +1,82 @@
+use luau::{state::LuauState, thread::ThreadResult, value::Value, Error};
+use luau_compiler::{compile, CompilerOptions};
+
+#[test]
+fn test_sandbox() {
+    let state = LuauState::new().unwrap();
+    state.sandbox().unwrap();
+    assert!(state.is_sandboxed());
+
+    let options = CompilerOptions::new();
+    assert_eq!(
+        state
+            .exec::<f64>("return math.floor(2.5)", &options)
+            .unwrap(),
+        2.0
+    );
+
+    // only the safe libraries are opened
+    let (debug, os): (Value, Value) = state.exec("return debug, os", &options).unwrap();
+    assert!(debug.is_nil() && os.is_nil());
+
+    // neither the globals nor the libraries can be modified
+    assert!(state.exec::<()>("x = 1", &options).is_err());
+    assert!(state.exec::<()>("math.pi = 3", &options).is_err());
+    assert_eq!(
+        state.exec::<f64>("return math.pi", &options).unwrap(),
+        std::f64::consts::PI
+    );
+}
+
+#[test]
+fn test_sandboxed_thread() {
+    let state = LuauState::new().unwrap();
+    state.sandbox().unwrap();
+
+    let bytecode = compile(
+        "x = (x or 0) + 1 return x + math.floor(1.5)",
+        &CompilerOptions::new(),
+    )
+    .unwrap();
+    let script = state.load("=test", &bytecode, None).unwrap();
+
+    let thread = state.create_thread(&script).unwrap().sandboxed().unwrap();
+    assert!(matches!(
+        thread.resume::<_, f64>(()),
+        ThreadResult::Finished(2.0)
+    ));
+
+    // the global stays in the thread, and survives a reset
+    thread.reset().unwrap();
+    assert!(matches!(
+        thread.resume::<_, f64>(()),
+        ThreadResult::Finished(3.0)
+    ));
+
+    let other = state.create_thread(&script).unwrap().sandboxed().unwrap();
+    assert!(matches!(
+        other.resume::<_, f64>(()),
+        ThreadResult::Finished(2.0)
+    ));
+
+    let global: Value = state.exec("return x", &CompilerOptions::new()).unwrap();
+    assert!(global.is_nil());
+}
+
+#[test]
+fn test_sandbox_mutable_globals() {
+    let state = LuauState::new().unwrap();
+
+    let mut options = CompilerOptions::new();
+    options.set_mutable_globals(["math"]);
+    assert!(state.validate_options(&options).is_ok());
+
+    state.sandbox().unwrap();
+    match state.exec::<()>("return", &options) {
+        Err(Error::ReadonlyGlobal(name)) => assert_eq!(name, "math"),
+        other => panic!("expected a readonly global error, got {other:?}"),
+    }
+
+    options.set_mutable_globals(["counter"]);
+    assert!(state.validate_options(&options).is_ok());
+}
@@ -0,0 +1,94 @@
+use luau::{load::LoadError, state::LuauState, Error};
+use luau_compiler::{compile, CompilerOptions};
+use luau_sys::common::bytecode::LuauOpcode;
+
+#[test]
+fn test_load_and_call() {
+    let state = LuauState::new().unwrap();
+    let bytecode = compile("return 1 + 2", &CompilerOptions::new()).unwrap();
+
+    let function = state.load("=test", &bytecode, None).unwrap();
+    assert_eq!(function.call::<_, f64>(()).unwrap(), 3.0);
+    // can be called again
+    assert_eq!(function.call::<_, f64>(()).unwrap(), 3.0);
+}
+
+#[test]
+fn test_exec() {
+    let state = LuauState::new().unwrap();
+    let opts = CompilerOptions::new();
+
+    assert_eq!(
+        state.exec::<String>("return 'a' .. 'b'", &opts).unwrap(),
+        "ab"
+    );
+    assert!(matches!(
+        state.exec::<()>("return (", &opts),
+        Err(Error::Compile(_))
+    ));
+    assert!(matches!(
+        state.exec::<()>("error('oops')", &opts),
+        Err(Error::Runtime { .. })
+    ));
+}
+
+#[test]
+fn test_load_errors() {
+    let state = LuauState::new().unwrap();
+
+    assert_eq!(
+        state.load("=test", &[], None).unwrap_err(),
+        LoadError::Truncated
+    );
+    assert!(matches!(
+        state.load("=test", &[255, 0], None),
+        Err(LoadError::Version { version: 255, .. })
+    ));
+
+    assert!(matches!(
+        state.load("=test", b"\0:1: oops", None),
+        Err(LoadError::Compile(_))
+    ));
+}
+
+#[test]
+fn test_load_verifies() {
+    let state = LuauState::new().unwrap();
+    let mut bytecode = compile("return 1234", &CompilerOptions::new())
+        .unwrap()
+        .to_vec();
+
+    // LOADN 0 1234, moved to a register way outside of the stack
+    let [lo, hi] = 1234i16.to_le_bytes();
+    let loadn = LuauOpcode::LOP_LOADN.0 as u8;
+    let at = bytecode
+        .windows(4)
+        .position(|w| w == [loadn, 0, lo, hi])
+        .unwrap();
+    bytecode[at + 1] = 200;
+
+    assert!(matches!(
+        state.load("=test", &bytecode, None),
+        Err(LoadError::Verify(_))
+    ));
+}
+
+#[test]
+fn test_load_env() {
+    let state = LuauState::new().unwrap();
+    let bytecode = compile("return x", &CompilerOptions::new()).unwrap();
+
+    let env = state.create_table(0, 1).unwrap();
+    env.set("x", 5.0).unwrap();
+    let function = state.load("=test", &bytecode, Some(&env)).unwrap();
+    assert_eq!(function.call::<_, f64>(()).unwrap(), 5.0);
+
+    // a table of another state is an error rather than a panic
+    let other = LuauState::new().unwrap();
+    let foreign = other.create_table(0, 0).unwrap();
+    assert_eq!(
+        state.load("=test", &bytecode, Some(&foreign)).unwrap_err(),
+        LoadError::ForeignEnv
+    );
+    assert_eq!(function.call::<_, f64>(()).unwrap(), 5.0);
+}
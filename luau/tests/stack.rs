@@ -0,0 +1,55 @@
+use luau::{state::LuauState, Error, IntoLuau};
+
+/// Fails to push after some values were already pushed by a tuple
+struct Unpushable;
+
+impl IntoLuau for Unpushable {
+    fn push(self, _state: &LuauState) -> luau::Result<()> {
+        Err(Error::ToLuau {
+            from: "Unpushable",
+            to: "anything",
+        })
+    }
+}
+
+fn top(state: &LuauState) -> i32 {
+    state.stack_guard(0).unwrap().top()
+}
+
+#[test]
+fn test_stack_balanced_on_error() {
+    let state = LuauState::new().unwrap();
+    let table = state.create_table(0, 0).unwrap();
+    let function = state.create_function(|_, ()| Ok::<_, Error>(())).unwrap();
+
+    let before = top(&state);
+
+    assert!(function.call::<_, ()>((1.0, "a", Unpushable)).is_err());
+    assert!(table.set("key", Unpushable).is_err());
+    assert!(table.push(vec![Unpushable]).is_err());
+
+    assert_eq!(top(&state), before);
+}
+
+#[test]
+fn test_stack_guard() {
+    let state = LuauState::new().unwrap();
+    let before = top(&state);
+
+    let table = {
+        let _guard = state.stack_guard(2).unwrap();
+        let _temporary = state.create_table(0, 0).unwrap();
+        // values still owned by handles are kept
+        state.create_table(0, 0).unwrap()
+    };
+    table.set("a", 1.0).unwrap();
+    assert_eq!(table.get::<_, f64>("a").unwrap(), 1.0);
+
+    drop(table);
+    assert_eq!(top(&state), before);
+
+    assert!(matches!(
+        state.stack_guard(10_000_000),
+        Err(Error::StackOverflow)
+    ));
+}
@@ -0,0 +1,52 @@
+use luau::{state::LuauState, StdLib};
+use luau_compiler::CompilerOptions;
+
+#[test]
+fn test_no_libs() {
+    let state = LuauState::new().unwrap();
+    assert!(state.opened_libs().is_empty());
+
+    // without the base library even `typeof` is missing
+    assert!(state
+        .exec::<()>("return typeof(1)", &CompilerOptions::new())
+        .is_err());
+}
+
+#[test]
+fn test_open_libs() {
+    let state = LuauState::new().unwrap();
+    let libs = StdLib::ALL - StdLib::DEBUG - StdLib::OS;
+    state.open_libs(libs).unwrap();
+
+    assert_eq!(state.opened_libs(), libs);
+    assert!(libs.contains(StdLib::BASE | StdLib::MATH));
+    assert!(!libs.contains(StdLib::OS));
+
+    let (os, debug, math): (String, String, String) = state
+        .exec(
+            "return typeof(os), typeof(debug), typeof(math)",
+            &CompilerOptions::new(),
+        )
+        .unwrap();
+    assert_eq!(
+        (os.as_str(), debug.as_str(), math.as_str()),
+        ("nil", "nil", "table")
+    );
+
+    let joined: String = state
+        .exec(
+            "return table.concat({string.upper('a'), tostring(bit32.band(6, 3))}, ',')",
+            &CompilerOptions::new(),
+        )
+        .unwrap();
+    assert_eq!(joined, "A,2");
+}
+
+#[test]
+fn test_stdlib_debug() {
+    assert_eq!(
+        format!("{:?}", StdLib::BASE | StdLib::UTF8),
+        r#"{"base", "utf8"}"#
+    );
+    assert_eq!(!StdLib::ALL, StdLib::NONE);
+}
@@ -0,0 +1,102 @@
+use luau::{state::LuauState, table::Table, Error};
+
+#[test]
+fn test_table_get_set() {
+    let state = LuauState::new().unwrap();
+    let table = state.create_table(0, 2).unwrap();
+
+    table.set("hello", "world").unwrap();
+    table.raw_set(1.0, true).unwrap();
+
+    assert_eq!(table.get::<_, String>("hello").unwrap(), "world");
+    assert_eq!(table.raw_get::<_, bool>(1.0).unwrap(), true);
+    assert_eq!(table.get::<_, Option<f64>>("missing").unwrap(), None);
+    assert!(
+        table.get::<_, f64>("hello").is_err(),
+        "string is not a number"
+    );
+}
+
+#[test]
+fn test_table_push_pop() {
+    let state = LuauState::new().unwrap();
+    let table = state.create_table(3, 0).unwrap();
+
+    table.push(1.0).unwrap();
+    table.push(2.0).unwrap();
+    table.push(3.0).unwrap();
+    assert_eq!(table.len(), 3);
+
+    assert_eq!(table.pop::<f64>().unwrap(), 3.0);
+    assert_eq!(table.len(), 2);
+
+    let sum: f64 = table.pairs::<f64, f64>().map(|pair| pair.unwrap().1).sum();
+    assert_eq!(sum, 3.0);
+
+    table.clear().unwrap();
+    assert!(table.is_empty());
+    assert_eq!(table.pop::<Option<f64>>().unwrap(), None);
+}
+
+#[test]
+fn test_table_metatable_readonly() {
+    let state = LuauState::new().unwrap();
+    let table = state.create_table(0, 0).unwrap();
+    let meta = state.create_table(0, 0).unwrap();
+
+    assert!(table.metatable().unwrap().is_none());
+    table.set_metatable(Some(&meta)).unwrap();
+    assert_eq!(table.metatable().unwrap(), Some(meta));
+
+    table.set_readonly(true);
+    assert!(table.is_readonly());
+    assert!(matches!(table.raw_set("a", 1.0), Err(Error::Readonly)));
+    assert!(
+        table.set("a", 1.0).is_err(),
+        "readonly tables can't be modified"
+    );
+    assert!(table.set_metatable(None).is_err());
+
+    table.set_readonly(false);
+    table.set("a", 1.0).unwrap();
+}
+
+#[test]
+fn test_table_handles_out_of_order() {
+    let state = LuauState::new().unwrap();
+
+    let a = state.create_table(0, 0).unwrap();
+    let b = state.create_table(0, 0).unwrap();
+    a.set("b", &b).unwrap();
+    drop(a);
+
+    let c = state.create_table(0, 0).unwrap();
+    b.set("c", &c).unwrap();
+    assert_eq!(b.get::<_, Table>("c").unwrap(), c);
+}
+
+#[test]
+fn test_globals() {
+    let state = LuauState::new().unwrap();
+
+    let double = state
+        .create_function(|_, x: f64| Ok::<_, Error>(x * 2.0))
+        .unwrap();
+    state.set_global("double", &double).unwrap();
+    state.set_global("scale", 3.0).unwrap();
+
+    let bytecode = luau_compiler::compile(
+        "width = double(scale) title = 'config'",
+        &luau_compiler::CompilerOptions::new(),
+    )
+    .unwrap();
+    let config = state.load("=config", &bytecode, None).unwrap();
+    config.call::<_, ()>(()).unwrap();
+
+    assert_eq!(state.get_global::<f64>("width").unwrap(), 6.0);
+    assert_eq!(state.get_global::<String>("title").unwrap(), "config");
+    assert_eq!(state.get_global::<Option<f64>>("missing").unwrap(), None);
+
+    let globals = state.globals().unwrap();
+    assert_eq!(globals.get::<_, f64>("scale").unwrap(), 3.0);
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use common::load;
+use luau::{
+    state::LuauState,
+    thread::{ThreadResult, ThreadStatus, Yield},
+    value::Value,
+    Error,
+};
+
+#[test]
+fn test_thread_yield() {
+    let state = LuauState::new().unwrap();
+
+    let double = state
+        .create_function(|_, x: f64| Ok::<_, Error>(Yield(x * 2.0)))
+        .unwrap();
+    let script = load(&state, "local double = ... return double(1) + 10");
+
+    let thread = state.create_thread(&script).unwrap();
+    assert_eq!(thread.status(), ThreadStatus::Suspended);
+
+    match thread.resume::<_, f64>(&double) {
+        ThreadResult::Yielded(x) => assert_eq!(x, 2.0),
+        other => panic!("expected the thread to yield, got {other:?}"),
+    }
+    assert!(thread.is_resumable());
+
+    // resume values are returned from the yielding function
+    match thread.resume::<_, f64>(5.0) {
+        ThreadResult::Finished(x) => assert_eq!(x, 15.0),
+        other => panic!("expected the thread to finish, got {other:?}"),
+    }
+    assert_eq!(thread.status(), ThreadStatus::Finished);
+    assert!(matches!(thread.resume::<_, ()>(()), ThreadResult::Error(_)));
+
+    // can't yield outside of a thread
+    assert!(double.call::<_, f64>(1.0).is_err());
+}
+
+#[test]
+fn test_thread_error() {
+    let state = LuauState::new().unwrap();
+
+    let script = load(&state, "local t = nil\nreturn t.field");
+    let thread = state.create_thread(&script).unwrap();
+
+    match thread.resume::<_, ()>(()) {
+        ThreadResult::Error(Error::Runtime {
+            message,
+            traceback: Some(traceback),
+        }) => {
+            assert!(message.starts_with("test:2:"));
+            assert!(traceback.contains("test:2"));
+        }
+        other => panic!("expected a runtime error with a traceback, got {other:?}"),
+    }
+    assert_eq!(thread.status(), ThreadStatus::Error);
+}
+
+#[test]
+fn test_thread_reset() {
+    let state = LuauState::new().unwrap();
+
+    let wait = state
+        .create_function(|_, ()| Ok::<_, Error>(Yield(())))
+        .unwrap();
+    let script = load(&state, "local wait, x = ... wait() return x");
+    let thread = state.create_thread(&script).unwrap();
+
+    assert!(matches!(
+        thread.resume::<_, ()>((&wait, 1.0)),
+        ThreadResult::Yielded(())
+    ));
+    thread.reset().unwrap();
+    assert_eq!(thread.status(), ThreadStatus::Suspended);
+
+    // starts over with new arguments
+    assert!(matches!(
+        thread.resume::<_, ()>((&wait, 2.0)),
+        ThreadResult::Yielded(())
+    ));
+    assert!(matches!(
+        thread.resume::<_, f64>(()),
+        ThreadResult::Finished(2.0)
+    ));
+}
+
+#[test]
+fn test_thread_value() {
+    let state = LuauState::new().unwrap();
+
+    let script = load(&state, "return 1");
+    let thread = state.create_thread(&script).unwrap();
+
+    let identity = load(&state, "local t = ... return t");
+    let value: Value = identity.call(&thread).unwrap();
+    match value {
+        Value::Thread(t) => assert_eq!(t, thread),
+        other => panic!("expected a thread, got {other:?}"),
+    }
+}
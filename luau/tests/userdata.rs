@@ -0,0 +1,227 @@
+mod common;
+
+use common::{load, Tracked};
+use luau::{
+    function,
+    state::LuauState,
+    table::Table,
+    userdata::{MetaMethod, UserDataMethods},
+    Error, UserData,
+};
+use luau_compiler::{compile, CompilerOptions};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Vec2 {
+    x: f64,
+    y: f64,
+}
+
+impl UserData for Vec2 {
+    const NAME: &'static str = "Vec2";
+
+    fn register(methods: &mut UserDataMethods<Self>) {
+        methods.add_field_getter("x", |_, this| Ok::<_, Error>(this.x));
+        methods.add_field_getter("y", |_, this| Ok::<_, Error>(this.y));
+        methods.add_field_setter("x", |_, this, x: f64| {
+            this.x = x;
+            Ok::<_, Error>(())
+        });
+        methods.add_method("length", |_, this, ()| Ok::<_, Error>(this.x.hypot(this.y)));
+        methods.add_method_mut("scale", |_, this, k: f64| {
+            this.x *= k;
+            this.y *= k;
+            Ok::<_, Error>(())
+        });
+        methods.add_meta_method(MetaMethod::Add, |_, this, other: Vec2| {
+            Ok::<_, Error>(Vec2 {
+                x: this.x + other.x,
+                y: this.y + other.y,
+            })
+        });
+        methods.add_meta_method(MetaMethod::Eq, |_, this, other: Vec2| {
+            Ok::<_, Error>(*this == other)
+        });
+        methods.add_meta_method(MetaMethod::Len, |_, _, ()| Ok::<_, Error>(2));
+        methods.add_meta_method(MetaMethod::Iter, |_, this, ()| {
+            let components = [this.x, this.y];
+            let next = function::wrap(move |_, (_, i): (Option<f64>, Option<usize>)| {
+                let i = i.map_or(1, |i| i + 1);
+                Ok::<_, Error>((
+                    components.get(i - 1).map(|_| i),
+                    components.get(i - 1).copied(),
+                ))
+            });
+
+            Ok::<_, Error>(next)
+        });
+    }
+}
+
+#[test]
+fn test_userdata() {
+    let state = LuauState::new().unwrap();
+    let a = state.create_userdata(Vec2 { x: 1.0, y: 4.0 }).unwrap();
+
+    let script = load(
+        &state,
+        r#"
+        local a, b = ...
+        a.x = 3
+        a:scale(2)
+
+        local sum = 0
+        for _, c in a do
+            sum += c
+        end
+
+        return a.x, a.y, a:length(), #a, a == b, (a + b).x, sum
+        "#,
+    );
+    let results: (f64, f64, f64, f64, bool, f64, f64) =
+        script.call((&a, Vec2 { x: 6.0, y: 8.0 })).unwrap();
+
+    assert_eq!(results, (6.0, 8.0, 10.0, 2.0, true, 12.0, 14.0));
+    assert!(a.is::<Vec2>());
+    assert_eq!(*a.borrow::<Vec2>().unwrap(), Vec2 { x: 6.0, y: 8.0 });
+}
+
+#[test]
+fn test_userdata_errors() {
+    let state = LuauState::new().unwrap();
+    let a = state.create_userdata(Vec2 { x: 1.0, y: 2.0 }).unwrap();
+
+    let unknown = load(&state, "local a = ... return a.z");
+    assert!(matches!(
+        unknown.call::<_, ()>(&a),
+        Err(Error::UnknownField {
+            type_name: "Vec2",
+            ..
+        })
+    ));
+
+    let readonly = load(&state, "local a = ... a.y = 1");
+    assert!(matches!(
+        readonly.call::<_, ()>(&a),
+        Err(Error::UnknownField {
+            type_name: "Vec2",
+            ..
+        })
+    ));
+
+    let wrong_self = load(&state, "local a = ... return a.length(5)");
+    assert!(matches!(
+        wrong_self.call::<_, f64>(&a),
+        Err(Error::FromLuau { to: "Vec2", .. })
+    ));
+}
+
+#[test]
+fn test_userdata_methods_only() {
+    #[derive(Default)]
+    struct Sum(f64);
+
+    impl UserData for Sum {
+        const NAME: &'static str = "Sum";
+
+        fn register(methods: &mut UserDataMethods<Self>) {
+            methods.add_method("get", |_, this, ()| Ok::<_, Error>(this.0));
+            methods.add_method_mut("add_all", |_, this, t: Table| {
+                for i in 1..=t.len() {
+                    this.0 += t.raw_get::<_, f64>(i)?;
+                }
+                Ok::<_, Error>(())
+            });
+        }
+    }
+
+    let state = LuauState::new().unwrap();
+    let sum = state.create_userdata(Sum::default()).unwrap();
+
+    let add = load(&state, "local s = ... s:add_all({1, 2, 3}) return s:get()");
+    assert_eq!(add.call::<_, f64>(&sum).unwrap(), 6.0);
+
+    // no fields and no __index metamethod, unknown keys are still errors rather than nil
+    let unknown = load(&state, "local s = ... return s.unknown");
+    assert!(matches!(
+        unknown.call::<_, ()>(&sum),
+        Err(Error::UnknownField {
+            type_name: "Sum",
+            ref field,
+        }) if field == "unknown"
+    ));
+}
+
+#[test]
+fn test_userdata_borrows() {
+    let state = LuauState::new().unwrap();
+    let a = state.create_userdata(Vec2 { x: 1.0, y: 2.0 }).unwrap();
+
+    {
+        let _shared = a.borrow::<Vec2>().unwrap();
+        assert!(a.borrow::<Vec2>().is_ok());
+        assert!(matches!(
+            a.borrow_mut::<Vec2>(),
+            Err(Error::UserDataBorrowMut("Vec2"))
+        ));
+    }
+
+    let length = load(&state, "local a = ... return a:length()");
+    {
+        let _exclusive = a.borrow_mut::<Vec2>().unwrap();
+        assert!(matches!(
+            length.call::<_, f64>(&a),
+            Err(Error::UserDataBorrow("Vec2"))
+        ));
+    }
+    assert!(length.call::<_, f64>(&a).is_ok());
+}
+
+#[test]
+fn test_userdata_registry() {
+    struct Marker;
+
+    impl UserData for Marker {
+        const NAME: &'static str = "Marker";
+    }
+
+    let state = LuauState::new().unwrap();
+    state.register_userdata::<Marker>().unwrap();
+    state.register_userdata::<Vec2>().unwrap();
+    // registering again keeps the tag
+    state.register_userdata::<Marker>().unwrap();
+    assert_eq!(state.userdata_types(), ["Marker", "Vec2"]);
+
+    let marker = state.create_userdata(Marker).unwrap();
+    assert_eq!(marker.type_name(), Some("Marker"));
+    assert!(matches!(
+        marker.borrow::<Vec2>(),
+        Err(Error::FromLuau {
+            from: "Marker",
+            to: "Vec2"
+        })
+    ));
+
+    let mut options = CompilerOptions::new();
+    options.set_userdata_types(state.userdata_types());
+    let bytecode = compile("local v: Vec2 = ... return v.x", &options).unwrap();
+    let function = state.load("=test", &bytecode, None).unwrap();
+    assert_eq!(
+        function.call::<_, f64>(Vec2 { x: 1.0, y: 2.0 }).unwrap(),
+        1.0
+    );
+}
+
+#[test]
+fn test_userdata_dropped() {
+    let counter = Arc::new(());
+
+    let state = LuauState::new().unwrap();
+    let tracked = state.create_userdata(Tracked(counter.clone())).unwrap();
+    assert!(!tracked.is::<Vec2>());
+    assert_eq!(Arc::strong_count(&counter), 2);
+
+    drop(tracked);
+    drop(state);
+    assert_eq!(Arc::strong_count(&counter), 1);
+}
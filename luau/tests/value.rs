@@ -0,0 +1,73 @@
+use luau::{state::LuauState, Value, Variadic};
+use luau_compiler::CompilerOptions;
+use std::collections::HashMap;
+
+#[test]
+fn test_value_roundtrip() {
+    let state = LuauState::new().unwrap();
+    let table = state.create_table(0, 0).unwrap();
+
+    table.set("n", 1.5).unwrap();
+    table.set("s", "text").unwrap();
+    table.set("v", [1.0f32, 2.0, 3.0]).unwrap();
+
+    assert!(matches!(
+        table.get::<_, Value>("n").unwrap(),
+        Value::Number(1.5)
+    ));
+    assert!(matches!(
+        table.get::<_, Value>("v").unwrap(),
+        Value::Vector([1.0, 2.0, 3.0])
+    ));
+    assert!(table.get::<_, Value>("missing").unwrap().is_nil());
+    match table.get::<_, Value>("s").unwrap() {
+        Value::String(s) => assert_eq!(s.to_str().unwrap(), "text"),
+        other => panic!("expected a string, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_integers() {
+    let state = LuauState::new().unwrap();
+    let table = state.create_table(0, 0).unwrap();
+
+    table.set("int", 42i32).unwrap();
+    table.set("frac", 0.5).unwrap();
+
+    assert_eq!(table.get::<_, u8>("int").unwrap(), 42);
+    assert_eq!(table.get::<_, Value>("int").unwrap().as_integer(), Some(42));
+    assert!(table.get::<_, i32>("frac").is_err(), "not an integer");
+    assert!(
+        table.set("big", i64::MAX).is_err(),
+        "can't be represented exactly"
+    );
+    assert!(table.set("neg", -1i32).is_ok());
+    assert!(table.get::<_, u32>("neg").is_err(), "out of range");
+}
+
+#[test]
+fn test_collections() {
+    let state = LuauState::new().unwrap();
+    let table = state.create_table(0, 0).unwrap();
+
+    table.set("list", vec![1, 2, 3]).unwrap();
+    let map = HashMap::from([("a".to_owned(), 1.0), ("b".to_owned(), 2.0)]);
+    table.set("map", map.clone()).unwrap();
+
+    assert_eq!(table.get::<_, Vec<i32>>("list").unwrap(), vec![1, 2, 3]);
+    assert_eq!(table.get::<_, HashMap<String, f64>>("map").unwrap(), map);
+}
+
+#[test]
+fn test_multiple_returns() {
+    let state = LuauState::new().unwrap();
+    let opts = CompilerOptions::new();
+
+    let (a, b, c): (i32, String, Option<bool>) = state.exec("return 1, 'two'", &opts).unwrap();
+    assert_eq!((a, b.as_str(), c), (1, "two", None));
+
+    let Variadic(all) = state
+        .exec::<Variadic<f64>>("return 1, 2, 3", &opts)
+        .unwrap();
+    assert_eq!(all, vec![1.0, 2.0, 3.0]);
+}
@@ -0,0 +1,54 @@
+use luau::{
+    state::LuauState,
+    vector::{Vector, VECTOR_SIZE},
+    StdLib, Value,
+};
+use luau_compiler::{CompilerOptions, Constant, LibraryWithKnownMembers};
+
+#[test]
+fn test_vector_conversions() {
+    let state = LuauState::new().unwrap();
+    state.open_libs(StdLib::VECTOR).unwrap();
+
+    let v: Vector = state
+        .exec("return vector.create(1, 2, 3)", &CompilerOptions::new())
+        .unwrap();
+    assert_eq!((v.x(), v.y(), v.z(), v.w()), (1.0, 2.0, 3.0, 0.0));
+    assert_eq!(<[f32; 3]>::from(v), [1.0, 2.0, 3.0]);
+    assert_eq!(<[f32; 4]>::from(v), [1.0, 2.0, 3.0, 0.0]);
+
+    let table = state.create_table(0, 0).unwrap();
+    table.set("v", [4.0f32, 5.0, 6.0, 7.0]).unwrap();
+
+    // the fourth component only survives in 4 wide vectors
+    let w = if VECTOR_SIZE == 4 { 7.0 } else { 0.0 };
+    assert_eq!(table.get::<_, [f32; 4]>("v").unwrap(), [4.0, 5.0, 6.0, w]);
+    assert_eq!(table.get::<_, [f32; 3]>("v").unwrap(), [4.0, 5.0, 6.0]);
+    match table.get::<_, Value>("v").unwrap() {
+        Value::Vector(v) => assert_eq!(Vector(v), Vector::from([4.0, 5.0, 6.0, 7.0])),
+        other => panic!("expected a vector, got {other:?}"),
+    }
+
+    assert!(table.get::<_, Vector>("missing").is_err());
+}
+
+#[test]
+fn test_vector_constants() {
+    let state = LuauState::new().unwrap();
+
+    let mut library = LibraryWithKnownMembers::new("space");
+    library
+        .constants
+        .insert("up".to_owned(), Vector::new(0.0, 1.0, 0.0).into());
+    let mut options = CompilerOptions::new();
+    options.add_known_library(library);
+
+    assert_eq!(
+        Constant::from(Vector::new(0.0, 1.0, 0.0)),
+        Constant::Vector(0.0, 1.0, 0.0, 0.0)
+    );
+
+    // folded by the compiler, the library doesn't exist at runtime
+    let up: Vector = state.exec("return space.up", &options).unwrap();
+    assert_eq!(up, Vector::new(0.0, 1.0, 0.0));
+}
@@ -1,13 +1,357 @@
 // This C++ shim provides wrappers around fallible luau functions
 // catching the exceptions and instead returning something closer to a rust's result type
 
-#include "../vendor/luau/VM/include/lua.h"
+#include "shim.h"
+
+#include <atomic>
+
+// internal VM headers, needed for running code in protected mode without pushing a closure first
+// (which could fail by itself)
+#include "ldo.h"
+#include "lstate.h"
+// the fastcall table, for checking builtin ids in untrusted bytecode
+#include "lbuiltins.h"
 
 namespace Shim {
 
-// Functions that returned void originally, just return a lua_Status now.
-lua_Status lua_createtable(lua_State* L, int narr, int nrec) {
-    
+// Runs `f` in protected mode, exactly the same way `lua_pcall` does it internally.
+//
+// `nargs` is the number of values on top of the stack that the wrapped function consumes. If an error is thrown
+// the call info and stack are restored and those values are replaced by the error object.
+template<typename F>
+static lua_Status protect(lua_State* L, int nargs, F f)
+{
+    ptrdiff_t old_top = savestack(L, L->top - nargs);
+
+    int status = luaD_pcall(
+        L,
+        [](lua_State*, void* ud)
+        {
+            (*static_cast<F*>(ud))();
+        },
+        &f,
+        old_top,
+        0
+    );
+
+    return lua_Status(status);
+}
+
+// Calls the rust function stored in the first upvalue, raising the error if it asks to
+static int rustTrampoline(lua_State* L)
+{
+    lua_CFunction fn = reinterpret_cast<lua_CFunction>(lua_tolightuserdata(L, lua_upvalueindex(1)));
+
+    int n = fn(L);
+    if (n == SHIM_RAISE_ERROR)
+        lua_error(L);
+
+    return n;
+}
+
+// The rust interrupt callback, see shim_lua_setinterrupt
+// Atomic since it is set by every VM, which may run on different threads
+static std::atomic<int (*)(lua_State* L, int gc)> rustInterrupt{nullptr};
+
+// Calls the rust interrupt callback, raising the error or yielding if it asks to
+static void interruptTrampoline(lua_State* L, int gc)
+{
+    // neither is possible in the middle of a gc step
+    if (gc >= 0)
+    {
+        rustInterrupt.load(std::memory_order_acquire)(L, gc);
+        return;
+    }
+
+    // space for the error object
+    lua_rawcheckstack(L, 1);
+
+    int action = rustInterrupt.load(std::memory_order_acquire)(L, gc);
+    if (action == SHIM_RAISE_ERROR)
+        lua_error(L);
+    else if (action == SHIM_INTERRUPT_YIELD)
+        lua_yield(L, 0);
+}
+
+} // namespace Shim
+
+using Shim::protect;
+
+// Stack manipulation
+/////////////////////
+
+lua_Status shim_lua_checkstack(lua_State* L, int sz, int* out)
+{
+    return protect(L, 0, [&] { *out = lua_checkstack(L, sz); });
+}
+
+lua_Status shim_lua_rawcheckstack(lua_State* L, int sz)
+{
+    return protect(L, 0, [&] { lua_rawcheckstack(L, sz); });
+}
+
+// Access functions
+///////////////////
+
+lua_Status shim_lua_tolstring(lua_State* L, int idx, size_t* len, const char** out)
+{
+    // converting a number to a string allocates
+    return protect(L, 0, [&] { *out = lua_tolstring(L, idx, len); });
+}
+
+lua_Status shim_lua_equal(lua_State* L, int idx1, int idx2, int* out)
+{
+    // may call the __eq metamethod
+    return protect(L, 0, [&] { *out = lua_equal(L, idx1, idx2); });
+}
+
+lua_Status shim_lua_lessthan(lua_State* L, int idx1, int idx2, int* out)
+{
+    // may call the __lt metamethod
+    return protect(L, 0, [&] { *out = lua_lessthan(L, idx1, idx2); });
+}
+
+// Push functions
+/////////////////
+
+lua_Status shim_lua_pushlstring(lua_State* L, const char* s, size_t l)
+{
+    return protect(L, 0, [&] { lua_pushlstring(L, s, l); });
+}
+
+lua_Status shim_lua_pushstring(lua_State* L, const char* s)
+{
+    return protect(L, 0, [&] { lua_pushstring(L, s); });
+}
+
+lua_Status shim_lua_pushcclosurek(lua_State* L, lua_CFunction fn, const char* debugname, int nup, lua_Continuation cont)
+{
+    // consumes the upvalues
+    return protect(L, nup, [&] { lua_pushcclosurek(L, fn, debugname, nup, cont); });
+}
+
+lua_Status shim_lua_pushrustclosure(lua_State* L, lua_CFunction fn, const char* debugname, int nup)
+{
+    // the rust function becomes the first upvalue, followed by the actual upvalues
+    return protect(
+        L,
+        nup,
+        [&]
+        {
+            lua_pushlightuserdata(L, reinterpret_cast<void*>(fn));
+            lua_insert(L, -(nup + 1));
+            lua_pushcclosurek(L, Shim::rustTrampoline, debugname, nup + 1, nullptr);
+        }
+    );
+}
+
+lua_Status shim_lua_newthread(lua_State* L, lua_State** out)
+{
+    return protect(L, 0, [&] { *out = lua_newthread(L); });
+}
+
+lua_Status shim_lua_newuserdatatagged(lua_State* L, size_t sz, int tag, void** out)
+{
+    return protect(L, 0, [&] { *out = lua_newuserdatatagged(L, sz, tag); });
+}
+
+lua_Status shim_lua_newuserdatataggedwithmetatable(lua_State* L, size_t sz, int tag, void** out)
+{
+    return protect(L, 0, [&] { *out = lua_newuserdatataggedwithmetatable(L, sz, tag); });
+}
+
+lua_Status shim_lua_newuserdatadtor(lua_State* L, size_t sz, void (*dtor)(void*), void** out)
+{
+    return protect(L, 0, [&] { *out = lua_newuserdatadtor(L, sz, dtor); });
+}
+
+lua_Status shim_lua_newbuffer(lua_State* L, size_t sz, void** out)
+{
+    return protect(L, 0, [&] { *out = lua_newbuffer(L, sz); });
+}
+
+// Get functions
+////////////////
+
+lua_Status shim_lua_gettable(lua_State* L, int idx, int* out)
+{
+    // consumes the key
+    return protect(L, 1, [&] { *out = lua_gettable(L, idx); });
+}
+
+lua_Status shim_lua_getfield(lua_State* L, int idx, const char* k, int* out)
+{
+    return protect(L, 0, [&] { *out = lua_getfield(L, idx, k); });
+}
+
+lua_Status shim_lua_rawgetfield(lua_State* L, int idx, const char* k, int* out)
+{
+    return protect(L, 0, [&] { *out = lua_rawgetfield(L, idx, k); });
+}
+
+lua_Status shim_lua_createtable(lua_State* L, int narr, int nrec)
+{
+    return protect(L, 0, [&] { lua_createtable(L, narr, nrec); });
+}
+
+// Set functions
+////////////////
+
+lua_Status shim_lua_settable(lua_State* L, int idx)
+{
+    // consumes the key and the value
+    return protect(L, 2, [&] { lua_settable(L, idx); });
+}
+
+lua_Status shim_lua_setfield(lua_State* L, int idx, const char* k)
+{
+    // consumes the value
+    return protect(L, 1, [&] { lua_setfield(L, idx, k); });
+}
+
+lua_Status shim_lua_rawsetfield(lua_State* L, int idx, const char* k)
+{
+    return protect(L, 1, [&] { lua_rawsetfield(L, idx, k); });
+}
+
+lua_Status shim_lua_rawset(lua_State* L, int idx)
+{
+    return protect(L, 2, [&] { lua_rawset(L, idx); });
+}
+
+lua_Status shim_lua_rawseti(lua_State* L, int idx, int n)
+{
+    return protect(L, 1, [&] { lua_rawseti(L, idx, n); });
+}
+
+lua_Status shim_lua_setmetatable(lua_State* L, int objindex, int* out)
+{
+    // consumes the metatable, throws if the object is a readonly table
+    return protect(L, 1, [&] { *out = lua_setmetatable(L, objindex); });
+}
+
+// Load and call
+////////////////
+
+lua_Status shim_luau_load(lua_State* L, const char* chunkname, const char* data, size_t size, int env, int* out)
+{
+    return protect(L, 0, [&] { *out = luau_load(L, chunkname, data, size, env); });
+}
+
+lua_Status shim_lua_call(lua_State* L, int nargs, int nresults)
+{
+    // consumes the function and the arguments
+    return protect(L, nargs + 1, [&] { lua_call(L, nargs, nresults); });
+}
+
+// Coroutine functions
+//////////////////////
+
+lua_Status shim_lua_yield(lua_State* L, int nresults, int* out)
+{
+    // throws if trying to yield across a C-call boundary
+    return protect(L, 0, [&] { *out = lua_yield(L, nresults); });
+}
+
+lua_Status shim_lua_break(lua_State* L, int* out)
+{
+    return protect(L, 0, [&] { *out = lua_break(L); });
+}
+
+// Garbage collection
+/////////////////////
+
+lua_Status shim_lua_gc(lua_State* L, int what, int data, int* out)
+{
+    return protect(L, 0, [&] { *out = lua_gc(L, what, data); });
+}
+
+// Miscellaneous functions
+//////////////////////////
+
+lua_Status shim_lua_next(lua_State* L, int idx, int* out)
+{
+    // consumes the key, throws if the key is not in the table
+    return protect(L, 1, [&] { *out = lua_next(L, idx); });
+}
+
+lua_Status shim_lua_concat(lua_State* L, int n)
+{
+    // may call the __concat metamethod
+    return protect(L, n, [&] { lua_concat(L, n); });
+}
+
+lua_Status shim_lua_clonefunction(lua_State* L, int idx)
+{
+    return protect(L, 0, [&] { lua_clonefunction(L, idx); });
+}
+
+lua_Status shim_lua_cleartable(lua_State* L, int idx)
+{
+    return protect(L, 0, [&] { lua_cleartable(L, idx); });
+}
+
+lua_Status shim_lua_ref(lua_State* L, int idx, int* out)
+{
+    // the registry may have to grow
+    return protect(L, 0, [&] { *out = lua_ref(L, idx); });
+}
+
+lua_Status shim_lua_setlightuserdataname(lua_State* L, int tag, const char* name)
+{
+    // the name is interned as a luau string
+    return protect(L, 0, [&] { lua_setlightuserdataname(L, tag, name); });
+}
+
+// Callbacks
+////////////
+
+void shim_lua_setinterrupt(lua_State* L, int (*interrupt)(lua_State* L, int gc))
+{
+    if (interrupt)
+    {
+        Shim::rustInterrupt.store(interrupt, std::memory_order_release);
+        lua_callbacks(L)->interrupt = Shim::interruptTrampoline;
+    }
+    else
+    {
+        lua_callbacks(L)->interrupt = nullptr;
+    }
+}
+
+// Bytecode
+///////////
+
+int shim_luau_hasfastcall(int bfid)
+{
+    return bfid > 0 && bfid < int(sizeof(luauF_table) / sizeof(luauF_table[0])) && luauF_table[bfid] != nullptr;
+}
+
+// Auxiliary library
+////////////////////
+
+lua_Status shim_luaL_newmetatable(lua_State* L, const char* tname, int* out)
+{
+    return protect(L, 0, [&] { *out = luaL_newmetatable(L, tname); });
+}
+
+lua_Status shim_luaL_tolstring(lua_State* L, int idx, size_t* len, const char** out)
+{
+    // may call the __tostring metamethod
+    return protect(L, 0, [&] { *out = luaL_tolstring(L, idx, len); });
+}
+
+lua_Status shim_luaL_openlibs(lua_State* L)
+{
+    return protect(L, 0, [&] { luaL_openlibs(L); });
+}
+
+lua_Status shim_luaL_sandbox(lua_State* L)
+{
+    return protect(L, 0, [&] { luaL_sandbox(L); });
 }
 
+lua_Status shim_luaL_sandboxthread(lua_State* L)
+{
+    return protect(L, 0, [&] { luaL_sandboxthread(L); });
 }
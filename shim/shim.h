@@ -0,0 +1,101 @@
+// Declarations of the exception-safe wrappers implemented in lua.cpp
+//
+// Every function here mirrors a luau VM function that can raise (allocation failure, metamethod errors,
+// readonly violations, ...). Instead of letting the C++ exception escape, the wrappers return the status code
+// and, in case of an error, leave the error object on top of the stack in place of the consumed arguments.
+// Return values of the original functions are written to the `out` pointer.
+//
+// All names here are prefixed with `shim_`, the prefix is stripped by bindgen so they show up
+// as `luau_sys::shim::lua_*` on the rust side.
+//
+// Rust functions can't raise errors themselves, since that would unwind through rust frames.
+// Instead they are pushed with `shim_lua_pushrustclosure` and return `SHIM_RAISE_ERROR`.
+
+#pragma once
+
+#include "lua.h"
+#include "lualib.h"
+
+// Rust functions pushed with shim_lua_pushrustclosure return this to have the error on top of the stack raised
+#define SHIM_RAISE_ERROR (-2)
+// Rust interrupt callbacks return this to yield the running thread
+#define SHIM_INTERRUPT_YIELD (-1)
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// Stack manipulation
+lua_Status shim_lua_checkstack(lua_State* L, int sz, int* out);
+lua_Status shim_lua_rawcheckstack(lua_State* L, int sz);
+
+// Access functions
+lua_Status shim_lua_tolstring(lua_State* L, int idx, size_t* len, const char** out);
+lua_Status shim_lua_equal(lua_State* L, int idx1, int idx2, int* out);
+lua_Status shim_lua_lessthan(lua_State* L, int idx1, int idx2, int* out);
+
+// Push functions
+lua_Status shim_lua_pushlstring(lua_State* L, const char* s, size_t l);
+lua_Status shim_lua_pushstring(lua_State* L, const char* s);
+lua_Status shim_lua_pushcclosurek(lua_State* L, lua_CFunction fn, const char* debugname, int nup, lua_Continuation cont);
+lua_Status shim_lua_pushrustclosure(lua_State* L, lua_CFunction fn, const char* debugname, int nup);
+lua_Status shim_lua_newthread(lua_State* L, lua_State** out);
+lua_Status shim_lua_newuserdatatagged(lua_State* L, size_t sz, int tag, void** out);
+lua_Status shim_lua_newuserdatataggedwithmetatable(lua_State* L, size_t sz, int tag, void** out);
+lua_Status shim_lua_newuserdatadtor(lua_State* L, size_t sz, void (*dtor)(void*), void** out);
+lua_Status shim_lua_newbuffer(lua_State* L, size_t sz, void** out);
+
+// Get functions
+lua_Status shim_lua_gettable(lua_State* L, int idx, int* out);
+lua_Status shim_lua_getfield(lua_State* L, int idx, const char* k, int* out);
+lua_Status shim_lua_rawgetfield(lua_State* L, int idx, const char* k, int* out);
+lua_Status shim_lua_createtable(lua_State* L, int narr, int nrec);
+
+// Set functions
+lua_Status shim_lua_settable(lua_State* L, int idx);
+lua_Status shim_lua_setfield(lua_State* L, int idx, const char* k);
+lua_Status shim_lua_rawsetfield(lua_State* L, int idx, const char* k);
+lua_Status shim_lua_rawset(lua_State* L, int idx);
+lua_Status shim_lua_rawseti(lua_State* L, int idx, int n);
+lua_Status shim_lua_setmetatable(lua_State* L, int objindex, int* out);
+
+// Load and call
+lua_Status shim_luau_load(lua_State* L, const char* chunkname, const char* data, size_t size, int env, int* out);
+lua_Status shim_lua_call(lua_State* L, int nargs, int nresults);
+
+// Coroutine functions
+lua_Status shim_lua_yield(lua_State* L, int nresults, int* out);
+lua_Status shim_lua_break(lua_State* L, int* out);
+
+// Garbage collection
+lua_Status shim_lua_gc(lua_State* L, int what, int data, int* out);
+
+// Miscellaneous functions
+lua_Status shim_lua_next(lua_State* L, int idx, int* out);
+lua_Status shim_lua_concat(lua_State* L, int n);
+lua_Status shim_lua_clonefunction(lua_State* L, int idx);
+lua_Status shim_lua_cleartable(lua_State* L, int idx);
+lua_Status shim_lua_ref(lua_State* L, int idx, int* out);
+lua_Status shim_lua_setlightuserdataname(lua_State* L, int tag, const char* name);
+
+// Callbacks
+// Sets the interrupt callback of the VM to call `interrupt`, or removes it if null. The rust function can't raise
+// errors itself, so it returns SHIM_RAISE_ERROR or SHIM_INTERRUPT_YIELD, or 0 to continue.
+// The same function is used for all VMs.
+void shim_lua_setinterrupt(lua_State* L, int (*interrupt)(lua_State* L, int gc));
+
+// Bytecode
+// Whether the VM has a fast implementation of the builtin function, the id of FASTCALL instructions. The VM calls
+// the implementation without checking that it exists.
+int shim_luau_hasfastcall(int bfid);
+
+// Auxiliary library
+lua_Status shim_luaL_newmetatable(lua_State* L, const char* tname, int* out);
+lua_Status shim_luaL_tolstring(lua_State* L, int idx, size_t* len, const char** out);
+lua_Status shim_luaL_openlibs(lua_State* L);
+lua_Status shim_luaL_sandbox(lua_State* L);
+lua_Status shim_luaL_sandboxthread(lua_State* L);
+
+#ifdef __cplusplus
+}
+#endif